@@ -0,0 +1,92 @@
+//! Times the reconciler on large trees. Run with `cargo bench`.
+
+use std::time::{Duration, Instant};
+
+use bevy_hooked::prelude::*;
+
+const ITEMS: u64 = 1000;
+const RUNS: u64 = 20;
+const TEXT_UPDATES: u32 = 1000;
+
+/// A permutation of the items that is the same on every run of the benchmark, so results stay
+/// comparable without pulling in a random number crate.
+fn shuffled(seed: u64) -> Vec<u64> {
+    let mut items = (0..ITEMS).collect::<Vec<_>>();
+    let mut state = seed;
+    for i in (1..items.len()).rev() {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        items.swap(i, (state >> 33) as usize % (i + 1));
+    }
+    items
+}
+
+fn list(order: &[u64]) -> Element {
+    e::node(e::keyed_list(
+        order.iter().copied(),
+        |n| *n,
+        |n| e::text(n.to_string()),
+    ))
+}
+
+fn report(name: &str, runs: u32, elapsed: Duration, operations: &str) {
+    println!(
+        "{}: {:?} per run over {} runs, {}",
+        name,
+        elapsed / runs,
+        runs,
+        operations
+    );
+}
+
+/// Changes the value of a mounted text primitive, which updates its `Text` in place.
+fn text_updates() {
+    let mut harness = TestHarness::new();
+    let mut root = harness.mount(e::text("0"));
+    let start = Instant::now();
+    for n in 1..=TEXT_UPDATES {
+        root = harness.replace_root(root, e::text(n.to_string()));
+    }
+    report(
+        "text_updates",
+        TEXT_UPDATES,
+        start.elapsed(),
+        "one text value changed per run",
+    );
+}
+
+/// Reorders a keyed list into a new permutation on every run.
+fn keyed_shuffle() {
+    let mut harness = TestHarness::new();
+    let first = shuffled(0);
+    let mut root = harness.mount(list(&first));
+    let orders = (1..=RUNS).map(shuffled).collect::<Vec<_>>();
+    let moved = orders
+        .iter()
+        .scan(&first, |previous, order| {
+            let moved = order.iter().zip(*previous).filter(|(a, b)| a != b).count();
+            *previous = order;
+            Some(moved)
+        })
+        .sum::<usize>();
+    let start = Instant::now();
+    for order in &orders {
+        root = harness.replace_root(root, list(order));
+    }
+    report(
+        "keyed_shuffle",
+        RUNS as u32,
+        start.elapsed(),
+        &format!(
+            "{} of {} children moved per run",
+            moved / RUNS as usize,
+            ITEMS
+        ),
+    );
+}
+
+fn main() {
+    text_updates();
+    keyed_shuffle();
+}
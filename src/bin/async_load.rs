@@ -0,0 +1,81 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context as TaskContext, Poll, Waker},
+    thread,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use bevy_hooked::prelude::*;
+
+fn main() {
+    App::build()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(HookedUiPlugin::with_font(app, "FiraMono-Medium.ttf"))
+        .add_startup_system(
+            (|mut commands: Commands| {
+                commands.spawn_bundle(UiCameraBundle::default());
+            })
+            .system(),
+        )
+        .run();
+}
+
+/// Stands in for a slow request, e.g. reading a save file or fetching a leaderboard.
+async fn fetch_high_score() -> u32 {
+    Delay::new(Duration::from_secs(2)).await;
+    4200
+}
+
+/// Completes after a duration. It waits on a thread of its own, as sleeping inside the future
+/// would block a thread of the task pool the whole time.
+struct Delay(Arc<Mutex<DelayState>>);
+
+#[derive(Default)]
+struct DelayState {
+    elapsed: bool,
+    waker: Option<Waker>,
+}
+
+impl Delay {
+    fn new(duration: Duration) -> Self {
+        let state = Arc::new(Mutex::new(DelayState::default()));
+        let timer = state.clone();
+        thread::spawn(move || {
+            thread::sleep(duration);
+            let mut state = timer.lock().unwrap();
+            state.elapsed = true;
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+        Self(state)
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<()> {
+        let mut state = self.0.lock().unwrap();
+        if state.elapsed {
+            Poll::Ready(())
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+fn high_score(ctx: Fctx) -> Element {
+    match ctx.use_async(fetch_high_score()) {
+        AsyncState::Pending => e::text("Loading high score..."),
+        AsyncState::Ready(score) => e::text(format!("High score: {}", score)),
+    }
+}
+
+fn app() -> Element {
+    high_score.e(())
+}
@@ -0,0 +1,30 @@
+use bevy::prelude::*;
+use bevy_hooked::prelude::*;
+
+fn main() {
+    App::build()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(HookedUiPlugin::with_font(app, "FiraMono-Medium.ttf"))
+        .add_startup_system(
+            (|mut commands: Commands| {
+                commands.spawn_bundle(UiCameraBundle::default());
+            })
+            .system(),
+        )
+        .run();
+}
+
+struct Clicks(u32);
+
+fn click_counter(ctx: Fctx) -> Element {
+    let (clicks, set_clicks) = ctx.use_linked_state(|| Clicks(0));
+
+    e::node([
+        e::text(format!("Clicked {} times", clicks.0)),
+        e::button([e::text("Click me")]).on_click(move |_| set_clicks.set(|mut c| c.0 += 1)),
+    ])
+}
+
+fn app() -> Element {
+    click_counter.e(())
+}
@@ -0,0 +1,33 @@
+use bevy::prelude::*;
+use bevy_hooked::prelude::*;
+
+fn main() {
+    App::build()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(HookedUiPlugin::with_font(app, "FiraMono-Medium.ttf"))
+        .add_startup_system(
+            (|mut commands: Commands| {
+                commands.spawn_bundle(UiCameraBundle::default());
+            })
+            .system(),
+        )
+        .run();
+}
+
+fn hover_button(ctx: Fctx) -> Element {
+    let hovered = ctx.use_hover();
+
+    let color = if hovered { Color::BLUE } else { Color::BLACK };
+    e::button([e::styled_node(
+        Style {
+            padding: Rect::all(Val::Px(10.)),
+            ..Default::default()
+        },
+        [e::styled_text("Hover me", 30., Color::WHITE)],
+    )
+    .background(color)])
+}
+
+fn app() -> Element {
+    hover_button.e(())
+}
@@ -0,0 +1,32 @@
+use bevy::prelude::*;
+use bevy_hooked::prelude::*;
+
+fn main() {
+    App::build()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(HookedUiPlugin::with_font(app, "FiraMono-Medium.ttf"))
+        .add_startup_system(
+            (|mut commands: Commands| {
+                commands.spawn_bundle(UiCameraBundle::default());
+            })
+            .system(),
+        )
+        .run();
+}
+
+fn long_list(ctx: Fctx) -> Element {
+    let scroll = ctx.use_scroll();
+
+    let rows = (1..=100)
+        .map(|i| e::text(format!("Row {}", i)))
+        .collect::<Vec<_>>();
+    e::scroll(
+        &scroll,
+        Size::new(Val::Px(300.), Val::Px(400.)),
+        e::node(rows),
+    )
+}
+
+fn app() -> Element {
+    long_list.e(())
+}
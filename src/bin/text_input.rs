@@ -0,0 +1,33 @@
+use bevy::prelude::*;
+use bevy_hooked::prelude::*;
+
+fn main() {
+    App::build()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(HookedUiPlugin::with_font(app, "FiraMono-Medium.ttf"))
+        .add_startup_system(
+            (|mut commands: Commands| {
+                commands.spawn_bundle(UiCameraBundle::default());
+            })
+            .system(),
+        )
+        .run();
+}
+
+struct Name(String);
+
+fn greeter(ctx: Fctx) -> Element {
+    let (name, set_name) = ctx.use_linked_state(|| Name(String::new()));
+
+    e::node([
+        e::text(format!("Hello, {}!", name.0)),
+        e::text_input(
+            name.0.clone(),
+            Callback::new(move |value| set_name.set(move |mut name| name.0 = value)),
+        ),
+    ])
+}
+
+fn app() -> Element {
+    greeter.e(())
+}
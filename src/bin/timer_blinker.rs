@@ -0,0 +1,34 @@
+use bevy::prelude::*;
+use bevy_hooked::prelude::*;
+
+fn main() {
+    App::build()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(HookedUiPlugin::with_font(app, "FiraMono-Medium.ttf"))
+        .add_startup_system(
+            (|mut commands: Commands| {
+                commands.spawn_bundle(UiCameraBundle::default());
+            })
+            .system(),
+        )
+        .run();
+}
+
+fn blinker(ctx: Fctx, period: &f32) -> Element {
+    let (elapsed, _) = ctx.use_timer(*period);
+    // Only the render an elapse triggers sees it, so this flips once per period.
+    let is_on = ctx.use_ref(|| false);
+    if elapsed {
+        is_on.with(|on| *on = !*on);
+    }
+
+    if is_on.get() {
+        e::text(format!("Yay! - Period = {}", period))
+    } else {
+        e::text(format!("Nay! - Period = {}", period))
+    }
+}
+
+fn app() -> Element {
+    e::node([blinker.e((1.,)), blinker.e((3.,)), blinker.e((5.,))])
+}
@@ -4,7 +4,7 @@ use bevy_hooked::prelude::*;
 fn main() {
     App::build()
         .add_plugins(DefaultPlugins)
-        .add_plugin(HookedUiPlugin(app))
+        .add_plugin(HookedUiPlugin::with_font(app, "FiraMono-Medium.ttf"))
         .add_startup_system(
             (|mut commands: Commands| {
                 commands.spawn_bundle(UiCameraBundle::default());
@@ -76,8 +76,8 @@ struct IntegerTimeSpent(u32);
 fn app() -> Element {
     e::node([
         counter.e(()),
-        full_blinker.e((1.,)),
-        simple_blinker.e((3.,)),
-        simple_blinker.e((5.,)),
+        full_blinker.e(1.),
+        simple_blinker.e(3.),
+        simple_blinker.e(5.),
     ])
 }
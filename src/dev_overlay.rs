@@ -0,0 +1,81 @@
+use bevy::prelude::*;
+
+use crate::prelude::DiffEvent;
+
+const FLASH_SECONDS: f32 = 0.5;
+const FLASH_ALPHA: f32 = 0.4;
+
+/// Briefly outlines every primitive the reconciler updated, to make accidental re-renders
+/// visible. Does nothing in release builds.
+pub struct HookedDevOverlay;
+
+impl Plugin for HookedDevOverlay {
+    fn build(&self, app: &mut AppBuilder) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        app.add_system(flash_updates.system())
+            .add_system(fade_flashes.system());
+    }
+}
+
+struct Flash {
+    remaining: f32,
+}
+
+fn flash_updates(
+    mut commands: Commands,
+    mut events: EventReader<DiffEvent>,
+    primitives: Query<(&Node, &GlobalTransform)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for event in events.iter() {
+        let id = match event {
+            DiffEvent::PrimitiveUpdated(id) => id,
+            _ => continue,
+        };
+        let (node, transform) = match primitives.get(id.entity()) {
+            Ok(primitive) => primitive,
+            Err(_) => continue,
+        };
+        // Flashes are root nodes, so their position is in window space. Bevy's UI y axis
+        // points up, which makes `top` the distance from the bottom of the window.
+        commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Px(transform.translation.x - node.size.x / 2.),
+                        top: Val::Px(transform.translation.y - node.size.y / 2.),
+                        ..Default::default()
+                    },
+                    size: Size::new(Val::Px(node.size.x), Val::Px(node.size.y)),
+                    ..Default::default()
+                },
+                material: materials.add(Color::rgba(1., 0.2, 0.2, FLASH_ALPHA).into()),
+                ..Default::default()
+            })
+            .insert(Flash {
+                remaining: FLASH_SECONDS,
+            });
+    }
+}
+
+fn fade_flashes(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut flashes: Query<(Entity, &mut Flash, &Handle<ColorMaterial>)>,
+) {
+    for (entity, mut flash, material) in flashes.iter_mut() {
+        flash.remaining -= time.delta_seconds();
+        if flash.remaining <= 0. {
+            materials.remove(material);
+            commands.entity(entity).despawn();
+        } else if let Some(material) = materials.get_mut(material) {
+            material
+                .color
+                .set_a(FLASH_ALPHA * flash.remaining / FLASH_SECONDS);
+        }
+    }
+}
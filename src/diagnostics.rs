@@ -0,0 +1,134 @@
+use std::{any::Any, fmt};
+
+use bevy::{
+    log::{error, warn},
+    prelude::{Entity, World},
+};
+
+use crate::internal::Key;
+
+/// Something the reconciler recovered from instead of panicking, reported to the
+/// [`HookedDiagnostics`] handler.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Diagnostic {
+    /// A render panicked. The subtree being rendered may be left half-updated.
+    RenderPanicked { message: String },
+    /// A component returned an `Err` and rendered nothing.
+    RenderFailed {
+        component: &'static str,
+        error: String,
+    },
+    /// Children were not mounted as they would go deeper than
+    /// [`Context::set_max_depth`](crate::prelude::Context::set_max_depth) allows. `chain` lists
+    /// the innermost components above them, outermost first.
+    TooDeep {
+        max: usize,
+        chain: Vec<&'static str>,
+    },
+    /// A primitive's parent was despawned before it mounted, so it was left unparented.
+    ParentDespawned { parent: Entity, primitive: Entity },
+    /// Several siblings rendered with the same key. All but the first are mounted from scratch
+    /// on every render.
+    DuplicateKey { key: Key },
+    /// State of a component was set after it unmounted, e.g. through a setter kept by a system
+    /// or a task. The update was dropped.
+    UnmountedUpdate { entity: Entity },
+    /// A hook couldn't take back a value its render handed out as a
+    /// [`Ref::Rc`](crate::prelude::Ref::Rc), as a clone of it was kept past the render. See the
+    /// hook for what happens to the value instead.
+    ValueStillShared {
+        hook: &'static str,
+        value: &'static str,
+    },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::RenderPanicked { message } => write!(f, "a render panicked: {}", message),
+            Diagnostic::RenderFailed { component, error } => {
+                write!(f, "`{}` failed to render: {}", component, error)
+            }
+            Diagnostic::TooDeep { max, chain } => write!(
+                f,
+                "not mounting children deeper than {} elements, below {}",
+                max,
+                chain.join(" > ")
+            ),
+            Diagnostic::ParentDespawned { parent, primitive } => write!(
+                f,
+                "parent primitive {:?} of {:?} was despawned before it mounted",
+                parent, primitive
+            ),
+            Diagnostic::DuplicateKey { key } => {
+                write!(f, "several siblings share the key {:?}", key)
+            }
+            Diagnostic::UnmountedUpdate { entity } => write!(
+                f,
+                "dropped an update to the state of {:?}, which is no longer mounted",
+                entity
+            ),
+            Diagnostic::ValueStillShared { hook, value } => write!(
+                f,
+                "`{}` couldn't take back its `{}`, a clone of it outlived the render",
+                hook, value
+            ),
+        }
+    }
+}
+
+/// Handles reconciler [`Diagnostic`]s, e.g. to fail a test or forward them to telemetry.
+/// Implemented by closures taking a `Diagnostic`.
+pub trait DiagnosticsHandler: Send + Sync + 'static {
+    fn report(&self, diagnostic: Diagnostic);
+}
+
+impl<F: Fn(Diagnostic) + Send + Sync + 'static> DiagnosticsHandler for F {
+    fn report(&self, diagnostic: Diagnostic) {
+        self(diagnostic)
+    }
+}
+
+/// The resource receiving every [`Diagnostic`]. By default they are logged with `bevy::log`.
+pub struct HookedDiagnostics(pub Box<dyn DiagnosticsHandler>);
+
+impl HookedDiagnostics {
+    pub fn new(handler: impl DiagnosticsHandler) -> Self {
+        Self(Box::new(handler))
+    }
+}
+
+impl Default for HookedDiagnostics {
+    fn default() -> Self {
+        Self::new(log_diagnostic)
+    }
+}
+
+fn log_diagnostic(diagnostic: Diagnostic) {
+    match diagnostic {
+        Diagnostic::ParentDespawned { .. }
+        | Diagnostic::DuplicateKey { .. }
+        | Diagnostic::UnmountedUpdate { .. } => {
+            warn!("bevy_hooked: {}", diagnostic)
+        }
+        _ => error!("bevy_hooked: {}", diagnostic),
+    }
+}
+
+/// Hands `diagnostic` to the [`HookedDiagnostics`] handler, or logs it if there is none.
+pub(crate) fn report(world: &World, diagnostic: Diagnostic) {
+    match world.get_resource::<HookedDiagnostics>() {
+        Some(diagnostics) => diagnostics.0.report(diagnostic),
+        None => log_diagnostic(diagnostic),
+    }
+}
+
+/// The message a panic was started with, for reporting a caught panic.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(|s| &**s))
+        .unwrap_or("<non-string panic payload>")
+        .to_string()
+}
@@ -1,125 +1,847 @@
 use bevy::{
     ecs::world::EntityMut,
+    math::{Rect, Size},
     prelude::{
-        BuildWorldChildren, ButtonBundle, Children, Color, Entity, Handle, ImageBundle, NodeBundle,
-        Parent, TextBundle, World,
+        Assets, BuildWorldChildren, ButtonBundle, Changed, Children, Color, ColorMaterial, Entity,
+        Handle, ImageBundle, NodeBundle, Parent, Quat, Query, TextBundle, Texture, Transform, Vec2,
+        Vec3, With, Without, World,
     },
     text::{Font, Text, TextStyle},
-    ui::{AlignItems, FlexDirection, Style},
+    transform::hierarchy::despawn_with_children_recursive,
+    ui::{AlignItems, FlexDirection, FocusPolicy, Interaction, Style, Val},
+    utils::HashMap,
 };
 
-use crate::FontHandle;
+use std::{fmt, sync::Arc};
 
-#[derive(Clone, Debug)]
+use crate::{
+    diagnostics::{report, Diagnostic},
+    internal::Callback,
+    scroll::ScrollViewport,
+    selection::TextSelection,
+    text_input::TextInputState,
+    FontHandle,
+};
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum PrimitiveData {
-    Node,
-    Text(String),
-    Image,
+    /// A layout node. Without a style it stacks its children top to bottom,
+    /// without a color it uses the default node material.
+    /// See [`Dom::set_children_order`] for how element order maps to visual order.
+    Node {
+        style: Option<Style>,
+        color: Option<Color>,
+    },
+    /// A single line of text. Whatever `style` leaves unset follows the [`Theme`] and the font
+    /// the plugin was set up with.
+    Text {
+        value: String,
+        style: TextOverrides,
+    },
+    /// A texture, sized to it unless `style` says otherwise.
+    Image {
+        texture: Handle<Texture>,
+        style: Option<Style>,
+    },
     Button,
+    /// A single line text field, editable while it is [`Focused`](crate::prelude::Focused).
+    /// `on_change` receives the edited text, which should be rendered back as `value`.
+    TextInput {
+        value: String,
+        on_change: Option<Callback<String>>,
+    },
+    /// Whatever [`RawSpawn`] inserts, for primitives this crate doesn't model.
+    Raw(RawSpawn),
 }
 
-#[derive(Clone, Debug)]
+impl PrimitiveData {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            PrimitiveData::Node { .. } => "Node",
+            PrimitiveData::Text { .. } => "Text",
+            PrimitiveData::Image { .. } => "Image",
+            PrimitiveData::Button => "Button",
+            PrimitiveData::TextInput { .. } => "TextInput",
+            PrimitiveData::Raw(_) => "Raw",
+        }
+    }
+}
+
+/// A primitive along with the attributes every kind of primitive supports.
+#[derive(Clone, Debug, PartialEq)]
 pub struct Primitive {
-    data: PrimitiveData,
+    pub data: PrimitiveData,
+    pub semantics: Option<Semantics>,
+    /// The [`StyleSheet`] class filling in whatever a node doesn't set inline.
+    pub class: Option<String>,
+    /// Lets the user select and copy a text primitive, see [`CopyText`](crate::prelude::CopyText).
+    pub selectable: bool,
+    pub transform: Option<UiTransform>,
+    /// Makes a node the viewport of a scroll area, see [`e::scroll`](crate::prelude::e::scroll).
+    pub scroll: Option<ScrollViewport>,
+    /// Runs when a button primitive is clicked.
+    pub on_click: Option<ClickHandler>,
 }
 
-#[derive(Clone, Copy, Hash, PartialEq, Eq)]
+impl Primitive {
+    /// Whether `self` and `other` describe the same primitive, leaving out the closures they
+    /// carry. Those never compare equal across renders, e.g. a new [`Callback`] every render.
+    pub(crate) fn same_shape(&self, other: &Primitive) -> bool {
+        let Primitive {
+            data,
+            semantics,
+            class,
+            selectable,
+            transform,
+            scroll,
+            on_click: _,
+        } = self;
+        let data = match (data, &other.data) {
+            (
+                PrimitiveData::TextInput { value, .. },
+                PrimitiveData::TextInput {
+                    value: other_value, ..
+                },
+            ) => value == other_value,
+            (PrimitiveData::Raw(_), PrimitiveData::Raw(_)) => true,
+            (data, other_data) => data == other_data,
+        };
+        data && *semantics == other.semantics
+            && *class == other.class
+            && *selectable == other.selectable
+            && *transform == other.transform
+            && *scroll == other.scroll
+    }
+}
+
+/// A callback run with the world when a button is clicked, see
+/// [`Element::on_click`](crate::prelude::Element::on_click). Handlers are never compared, so two
+/// primitives differing only in their handler count as equal.
+#[derive(Clone)]
+pub struct ClickHandler(pub Arc<dyn Fn(&mut World) + Send + Sync>);
+
+impl fmt::Debug for ClickHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ClickHandler")
+    }
+}
+
+impl PartialEq for ClickHandler {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+/// Sets up a raw primitive by inserting components into its entity, see
+/// [`e::raw`](crate::prelude::e::raw). Closures can't be compared, so it is run again on every
+/// diff, and a raw primitive is never equal to another.
+#[derive(Clone)]
+pub struct RawSpawn(pub Arc<dyn Fn(&mut EntityMut) + Send + Sync>);
+
+impl fmt::Debug for RawSpawn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RawSpawn")
+    }
+}
+
+impl PartialEq for RawSpawn {
+    fn eq(&self, _: &Self) -> bool {
+        false
+    }
+}
+
+/// A visual-only transform applied on top of a primitive's layout. It moves, rotates and scales
+/// the primitive along with everything inside it, but the flex layout of it and its siblings is
+/// computed as if it wasn't there.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UiTransform {
+    /// Offset from the laid out position, in pixels.
+    pub translation: Vec2,
+    /// Counterclockwise rotation around the primitive's center, in radians.
+    pub rotation: f32,
+    pub scale: Vec2,
+}
+
+impl Default for UiTransform {
+    fn default() -> Self {
+        Self {
+            translation: Vec2::ZERO,
+            rotation: 0.,
+            scale: Vec2::ONE,
+        }
+    }
+}
+
+/// Runs after layout, which resets the translation of every node each frame.
+pub(crate) fn apply_ui_transforms(mut primitives: Query<(&UiTransform, &mut Transform)>) {
+    for (ui_transform, mut transform) in primitives.iter_mut() {
+        transform.translation += ui_transform.translation.extend(0.);
+        transform.rotation = Quat::from_rotation_z(ui_transform.rotation);
+        transform.scale = ui_transform.scale.extend(1.);
+    }
+}
+
+/// Semantic metadata inserted as a component on primitives that set any.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Semantics {
+    pub role: Option<Role>,
+    pub label: Option<String>,
+    pub value: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Role {
+    Button,
+    Heading,
+    List,
+    ListItem,
+    Image,
+    Text,
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
 pub struct PrimitiveId(pub Entity);
 
+impl PrimitiveId {
+    /// The UI entity this primitive is rendered as.
+    pub fn entity(&self) -> Entity {
+        self.0
+    }
+}
+
+/// Defaults primitives use for whatever they don't set themselves. Replacing or mutating this
+/// resource restyles every primitive relying on it, without re-rendering any component.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub text_color: Color,
+    /// The color of selected text.
+    pub selection_color: Color,
+    pub font_size: f32,
+    /// The base unit of the spacing scale, see [`Theme::space`].
+    pub spacing: f32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            text_color: Color::BLACK,
+            selection_color: Color::rgb(0.2, 0.4, 0.9),
+            font_size: 30.,
+            spacing: 8.,
+        }
+    }
+}
+
+impl Theme {
+    /// Returns `steps` units of the spacing scale, in pixels.
+    pub fn space(&self, steps: f32) -> f32 {
+        self.spacing * steps
+    }
+}
+
+/// Overrides of the themed look of a text primitive.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextOverrides {
+    /// Font size before [`UiScale`] is applied.
+    pub size: Option<f32>,
+    pub color: Option<Color>,
+    pub font: Option<Handle<Font>>,
+}
+
+/// Marks text primitives that take their style from the [`Theme`], along with what they
+/// override.
+pub(crate) struct Themed(pub(crate) TextOverrides);
+
+/// An app-level zoom, on top of the window's scale factor. Font sizes and pixel dimensions of
+/// every primitive are multiplied by it, and changing it restyles the whole UI in place.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UiScale(pub f32);
+
+impl Default for UiScale {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+/// The style a node was rendered with, before [`UiScale`] was applied.
+pub(crate) struct Unscaled(Style);
+
+/// Where a primitive is among its parent's Bevy `Children`, so removing or moving it doesn't
+/// have to search for it. Only trusted after checking the parent's `Children` agree, as Bevy's
+/// own hierarchy commands don't keep it up to date.
+pub(crate) struct ChildIndex(usize);
+
+/// Named node styles, applied with [`Element::class`](crate::prelude::Element::class).
+/// Replacing or mutating this resource restyles every node using a class, without
+/// re-rendering any component.
+#[derive(Clone, Debug, Default)]
+pub struct StyleSheet {
+    classes: HashMap<String, StyleClass>,
+}
+
+impl StyleSheet {
+    pub fn with_class(mut self, name: impl Into<String>, class: StyleClass) -> Self {
+        self.insert(name, class);
+        self
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, class: StyleClass) {
+        self.classes.insert(name.into(), class);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&StyleClass> {
+        self.classes.get(name)
+    }
+}
+
+/// The defaults a class gives a node. Inline values win: a node with its own style keeps it
+/// entirely and only takes the color from its class, and the other way around.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StyleClass {
+    pub style: Option<Style>,
+    pub color: Option<Color>,
+}
+
+/// Remembers a node's class and inline values, so it can be restyled when the sheet changes.
+pub(crate) struct Classed {
+    class: String,
+    style: Option<Style>,
+    color: Option<Color>,
+}
+
+impl Classed {
+    fn of(primitive: &Primitive) -> Option<Self> {
+        match (&primitive.class, &primitive.data) {
+            (Some(class), PrimitiveData::Node { style, color }) => Some(Self {
+                class: class.clone(),
+                style: style.clone(),
+                color: *color,
+            }),
+            _ => None,
+        }
+    }
+}
+
 pub struct Dom<'a> {
     pub(crate) world: &'a mut World,
-    pub(crate) cursor: usize,
 }
 
 impl<'a> Dom<'a> {
     pub fn mount_as_child(
         &mut self,
-        primitive: PrimitiveData,
+        primitive: Primitive,
         parent: Option<PrimitiveId>,
     ) -> PrimitiveId {
-        let font = self.world.get_resource::<FontHandle>().unwrap().0.clone();
-        let mut entity = self.world.spawn();
-        helper(&mut entity, primitive, font);
-        let id = entity.id();
+        let id = self.world.spawn().id();
+        self.insert_primitive(id, primitive);
         if let Some(pid) = parent {
-            self.world
-                .entity_mut(pid.0)
-                .insert_children(self.cursor, &[id]);
+            if self.world.get_entity(pid.0).is_some() {
+                self.push_child(pid, id);
+            } else {
+                // Leaves the primitive unparented instead of taking the whole UI down.
+                report(
+                    self.world,
+                    Diagnostic::ParentDespawned {
+                        parent: pid.0,
+                        primitive: id,
+                    },
+                );
+            }
         }
-        self.cursor += 1;
         PrimitiveId(id)
     }
-    pub fn diff_primitive(&mut self, old: PrimitiveId, new: PrimitiveData) {
+    /// Turns an existing entity into `primitive`, for handing parts of a UI built without this
+    /// crate over to the reconciler. The entity keeps its place in the hierarchy, but the
+    /// components of any UI bundle it had are replaced and its children are despawned.
+    /// A primitive is mounted as usual if `existing` doesn't exist.
+    pub fn adopt(
+        &mut self,
+        existing: Entity,
+        primitive: Primitive,
+        parent: Option<PrimitiveId>,
+    ) -> PrimitiveId {
+        let (id, children) = self.adopt_detaching(existing, primitive, parent);
+        for child in children {
+            despawn_with_children_recursive(self.world, child);
+        }
+        id
+    }
+    /// Like [`Dom::adopt`], but detaches the children of `existing` instead, returning them for
+    /// the primitive's own children to adopt.
+    pub(crate) fn adopt_detaching(
+        &mut self,
+        existing: Entity,
+        primitive: Primitive,
+        parent: Option<PrimitiveId>,
+    ) -> (PrimitiveId, Vec<Entity>) {
+        if self.world.get_entity(existing).is_none() {
+            return (self.mount_as_child(primitive, parent), Vec::new());
+        }
+        let children = self
+            .world
+            .entity_mut(existing)
+            .remove::<Children>()
+            .map_or_else(Vec::new, |children| children.to_vec());
+        for &child in &children {
+            self.world.entity_mut(child).remove::<Parent>();
+        }
+        let mut entity = self.world.entity_mut(existing);
+        entity.remove_bundle_intersection::<NodeBundle>();
+        entity.remove_bundle_intersection::<TextBundle>();
+        entity.remove_bundle_intersection::<ImageBundle>();
+        entity.remove_bundle_intersection::<ButtonBundle>();
+        self.insert_primitive(existing, primitive);
+        if let Some(pid) = parent {
+            self.push_child(pid, existing);
+        }
+        (PrimitiveId(existing), children)
+    }
+    fn push_child(&mut self, parent: PrimitiveId, child: Entity) {
+        let mut parent = self.world.entity_mut(parent.0);
+        parent.push_children(&[child]);
+        let index = parent.get::<Children>().unwrap().len() - 1;
+        self.set_child_index(child, index);
+    }
+    /// The position of `child` in `children`, looked up through its [`ChildIndex`] when that is
+    /// up to date.
+    fn child_index(&self, children: &[Entity], child: Entity) -> Option<usize> {
+        self.world
+            .get::<ChildIndex>(child)
+            .map(|index| index.0)
+            .filter(|&index| children.get(index) == Some(&child))
+            .or_else(|| children.iter().position(|entity| *entity == child))
+    }
+    /// Updates the [`ChildIndex`] of every entity in `children[from..]`.
+    fn index_children(&mut self, children: &[Entity], from: usize) {
+        for (index, &child) in children.iter().enumerate().skip(from) {
+            self.set_child_index(child, index);
+        }
+    }
+    fn set_child_index(&mut self, child: Entity, index: usize) {
+        // Children despawned without being detached linger until their parent is reordered.
+        if let Some(mut child) = self.world.get_entity_mut(child) {
+            child.insert(ChildIndex(index));
+        }
+    }
+    fn replace_children(&mut self, parent: PrimitiveId, children: &[Entity]) {
+        self.index_children(children, 0);
+        *self.world.get_mut::<Children>(parent.0).unwrap() = Children::with(children);
+    }
+    fn insert_primitive(&mut self, id: Entity, primitive: Primitive) {
+        let font = self.world.get_resource::<FontHandle>().unwrap().0.clone();
+        let classed = Classed::of(&primitive);
+        let data = self.apply_class(primitive.data, primitive.class.as_deref());
+        let material = self.material_for(None, &data);
+        let theme = self.theme();
+        let scale = self.ui_scale();
+        let mut entity = self.world.entity_mut(id);
+        helper(&mut entity, data, font, material, &theme, scale);
+        if let Some(semantics) = primitive.semantics {
+            entity.insert(semantics);
+        }
+        if primitive.selectable && entity.contains::<Text>() {
+            entity.insert(TextSelection::default());
+        }
+        if let Some(classed) = classed {
+            entity.insert(classed);
+        }
+        if let Some(transform) = primitive.transform {
+            entity.insert(transform);
+        }
+        if let Some(scroll) = primitive.scroll {
+            entity.insert(scroll);
+        }
+        if let Some(on_click) = primitive.on_click {
+            entity.insert(on_click);
+        }
+    }
+    pub fn diff_primitive(&mut self, old: PrimitiveId, new: Primitive) {
         let font = self.world.get_resource::<FontHandle>().unwrap().0.clone();
+        let classed = Classed::of(&new);
+        let data = self.apply_class(new.data, new.class.as_deref());
+        let material = self.material_for(Some(old.0), &data);
+        let theme = self.theme();
+        let scale = self.ui_scale();
         let mut entity = self.world.entity_mut(old.0);
-        let kind = entity.remove::<PrimitiveKind>().unwrap();
-        match kind {
-            PrimitiveKind::Node => {
-                entity.remove_bundle::<NodeBundle>();
+        entity.remove::<Classed>();
+        let text_in_place = matches!(entity.get::<PrimitiveKind>(), Some(PrimitiveKind::Text));
+        let input_in_place = matches!(
+            entity.get::<PrimitiveKind>(),
+            Some(PrimitiveKind::TextInput)
+        );
+        let node_in_place = matches!(entity.get::<PrimitiveKind>(), Some(PrimitiveKind::Node));
+        let image_in_place = matches!(entity.get::<PrimitiveKind>(), Some(PrimitiveKind::Image));
+        let button_in_place = matches!(entity.get::<PrimitiveKind>(), Some(PrimitiveKind::Button));
+        match data {
+            // Text changes the most often, so it's updated without touching the rest of the entity.
+            PrimitiveData::Text { value, style } if text_in_place => {
+                let restyle = entity
+                    .get::<Themed>()
+                    .map_or(true, |themed| themed.0 != style);
+                let mut text = entity.get_mut::<Text>().unwrap();
+                // Selection highlighting may have split the text, it's split again afterwards.
+                if restyle {
+                    text.sections.truncate(1);
+                    text.sections[0].style = text_style(&style, font, &theme, scale);
+                }
+                if text.sections.len() > 1 || text.sections[0].value != value {
+                    text.sections.truncate(1);
+                    text.sections[0].value = value;
+                }
+                if restyle {
+                    entity.insert(Themed(style));
+                }
             }
-            PrimitiveKind::Text => {
-                entity.remove_bundle::<TextBundle>();
+            // Rebuilding the field on every keystroke would lose its interaction state.
+            PrimitiveData::TextInput { value, on_change } if input_in_place => {
+                let mut text = entity.get_mut::<Text>().unwrap();
+                if text.sections[0].value != value {
+                    text.sections[0].value = value.clone();
+                }
+                entity.insert(TextInputState { value, on_change });
             }
-            PrimitiveKind::Image => {
-                entity.remove_bundle::<ImageBundle>();
+            // Switching layouts, e.g. from a row to a column, only restyles the node.
+            PrimitiveData::Node { style, .. } if node_in_place => {
+                let style = style.unwrap_or_else(default_node_style);
+                if entity.get::<Unscaled>().map_or(true, |old| old.0 != style) {
+                    entity.insert(scaled(&style, scale));
+                    entity.insert(Unscaled(style));
+                }
+                entity.insert(material.unwrap_or_default());
             }
-            PrimitiveKind::Button => {
-                entity.remove_bundle::<ButtonBundle>();
+            // A new texture only changes the material, which was updated above.
+            PrimitiveData::Image { style, .. } if image_in_place => {
+                let style = style.unwrap_or_default();
+                if entity.get::<Unscaled>().map_or(true, |old| old.0 != style) {
+                    entity.insert(scaled(&style, scale));
+                    entity.insert(Unscaled(style));
+                }
+                entity.insert(material.unwrap_or_default());
+            }
+            // Rebuilding the bundle would reset its interaction, e.g. the hover it's drawn with.
+            PrimitiveData::Button if button_in_place => {
+                entity.insert(material.unwrap_or_default());
+            }
+            data => {
+                let kind = entity.remove::<PrimitiveKind>().unwrap();
+                entity.remove::<Themed>();
+                entity.remove::<Unscaled>();
+                match kind {
+                    PrimitiveKind::Node => {
+                        entity.remove_bundle::<NodeBundle>();
+                    }
+                    PrimitiveKind::Text => {
+                        entity.remove_bundle::<TextBundle>();
+                    }
+                    PrimitiveKind::Image => {
+                        entity.remove_bundle::<ImageBundle>();
+                    }
+                    PrimitiveKind::Button => {
+                        entity.remove_bundle::<ButtonBundle>();
+                    }
+                    PrimitiveKind::TextInput => {
+                        entity.remove_bundle::<TextBundle>();
+                        entity.remove::<Interaction>();
+                        entity.remove::<FocusPolicy>();
+                        entity.remove::<TextInputState>();
+                    }
+                    // What it inserted is unknown. Raw primitives overwrite it on the next run,
+                    // other kinds leave anything their bundle doesn't include in place.
+                    PrimitiveKind::Raw => {}
+                }
+                helper(&mut entity, data, font, material, &theme, scale);
+            }
+        }
+        if let Some(semantics) = new.semantics {
+            entity.insert(semantics);
+        } else {
+            entity.remove::<Semantics>();
+        }
+        if !(new.selectable && entity.contains::<Text>()) {
+            entity.remove::<TextSelection>();
+        } else if !entity.contains::<TextSelection>() {
+            entity.insert(TextSelection::default());
+        }
+        if let Some(transform) = new.transform {
+            entity.insert(transform);
+        } else if entity.remove::<UiTransform>().is_some() {
+            if let Some(mut transform) = entity.get_mut::<Transform>() {
+                transform.rotation = Quat::IDENTITY;
+                transform.scale = Vec3::ONE;
             }
         }
-        self.cursor += 1;
-        helper(&mut entity, new, font);
+        if let Some(scroll) = new.scroll {
+            entity.insert(scroll);
+        } else {
+            entity.remove::<ScrollViewport>();
+        }
+        if let Some(on_click) = new.on_click {
+            entity.insert(on_click);
+        } else {
+            entity.remove::<ClickHandler>();
+        }
+        if let Some(classed) = classed {
+            entity.insert(classed);
+        }
     }
     pub fn remove(&mut self, id: PrimitiveId) {
         if let Some(parent) = self.world.entity_mut(id.0).get::<Parent>().copied() {
-            let mut children = self
-                .world
-                .entity_mut(parent.0)
-                .get_mut::<Children>()
-                .unwrap();
-            let new = children
-                .iter()
-                .copied()
-                .filter(|e| *e != id.0)
-                .collect::<Vec<_>>();
-            *children = Children::with(&new);
+            let children = self.world.get::<Children>(parent.0).unwrap();
+            if let Some(index) = self.child_index(children, id.0) {
+                let mut new = children.to_vec();
+                new.remove(index);
+                self.index_children(&new, index);
+                *self.world.get_mut::<Children>(parent.0).unwrap() = Children::with(&new);
+            }
         }
         self.world.despawn(id.0);
     }
+    /// Despawns a primitive without detaching it from its parent, for when the parent is
+    /// despawned as well or its children are about to be reordered anyway.
+    pub(crate) fn despawn(&mut self, id: PrimitiveId) {
+        self.world.despawn(id.0);
+    }
+    /// Reorders the children of `parent` to `order`, which holds the same entities.
+    ///
+    /// Children are always kept in element order, so where the first element shows up only
+    /// depends on the node's `FlexDirection`: left for `Row`, right for `RowReverse`, top for
+    /// `ColumnReverse` and bottom for `Column`, as Bevy's UI y axis points up.
+    ///
+    /// Children already in place are left alone and the rest are swapped into place, finding
+    /// each through its [`ChildIndex`], so `Children` is only touched if the order changed.
+    /// It is rebuilt if children were despawned without being detached.
+    pub fn set_children_order(&mut self, parent: PrimitiveId, order: &[Entity]) {
+        let mut current = match self.world.get::<Children>(parent.0) {
+            Some(children) => children.to_vec(),
+            None => return,
+        };
+        if current.len() != order.len() {
+            return self.replace_children(parent, order);
+        }
+        let mut swaps = Vec::new();
+        for (index, &child) in order.iter().enumerate() {
+            if current[index] == child {
+                continue;
+            }
+            let from = match self.child_index(&current, child) {
+                Some(from) => from,
+                None => return self.replace_children(parent, order),
+            };
+            current.swap(index, from);
+            self.set_child_index(child, index);
+            self.set_child_index(current[from], from);
+            swaps.push((index, from));
+        }
+        if !swaps.is_empty() {
+            let mut children = self.world.get_mut::<Children>(parent.0).unwrap();
+            for (a, b) in swaps {
+                children.swap(a, b);
+            }
+        }
+    }
+    /// The mounted primitives as a tree, one per root primitive, for assertions and tooling.
+    ///
+    /// This returns a `Vec` since every mounted root and every primitive spawned outside of one
+    /// is a tree of its own, and it takes `&mut self` since querying a `World` needs mutable
+    /// access to it.
+    pub fn dump_tree(&mut self) -> Vec<PrimitiveNode> {
+        let mut roots = self
+            .world
+            .query_filtered::<Entity, (With<PrimitiveKind>, Without<Parent>)>()
+            .iter(self.world)
+            .collect::<Vec<_>>();
+        roots.sort();
+        roots
+            .into_iter()
+            .filter_map(|root| PrimitiveNode::of(self.world, root))
+            .collect()
+    }
+    /// Runs the handlers of the buttons that were just clicked.
+    pub(crate) fn run_click_handlers(&mut self) {
+        let mut query = self
+            .world
+            .query_filtered::<(&Interaction, &ClickHandler), Changed<Interaction>>();
+        let handlers = query
+            .iter(self.world)
+            .filter(|(interaction, _)| **interaction == Interaction::Clicked)
+            .map(|(_, handler)| handler.0.clone())
+            .collect::<Vec<_>>();
+        for handler in handlers {
+            handler(self.world);
+        }
+    }
+    /// Restyles every themed primitive with the current [`Theme`].
+    pub(crate) fn apply_theme(&mut self) {
+        let theme = self.theme();
+        let scale = self.ui_scale();
+        let mut query = self
+            .world
+            .query::<(&mut Text, &Themed, Option<&TextSelection>)>();
+        for (mut text, themed, selection) in query.iter_mut(self.world) {
+            let (start, end) = selection.map_or((0, 0), TextSelection::range);
+            let mut offset = 0;
+            for section in &mut text.sections {
+                // Highlighting gives the selected characters a section of their own.
+                let selected = offset >= start && offset < end;
+                offset += section.value.chars().count();
+                section.style.color = if selected {
+                    theme.selection_color
+                } else {
+                    themed.0.color.unwrap_or(theme.text_color)
+                };
+                section.style.font_size = themed.0.size.unwrap_or(theme.font_size) * scale;
+            }
+        }
+        // Text inputs are sized to fit a line at the theme's font size.
+        let mut inputs = self
+            .world
+            .query_filtered::<(&mut Unscaled, &mut Style), With<TextInputState>>();
+        for (mut unscaled, mut style) in inputs.iter_mut(self.world) {
+            unscaled.0.min_size.height = Val::Px(theme.font_size);
+            *style = scaled(&unscaled.0, scale);
+        }
+    }
+    /// Rescales every primitive with the current [`UiScale`].
+    pub(crate) fn apply_ui_scale(&mut self) {
+        self.apply_theme();
+        let scale = self.ui_scale();
+        let mut query = self.world.query::<(&Unscaled, &mut Style)>();
+        for (unscaled, mut style) in query.iter_mut(self.world) {
+            *style = scaled(&unscaled.0, scale);
+        }
+    }
+    /// Restyles every node using a class with the current [`StyleSheet`].
+    pub(crate) fn apply_style_sheet(&mut self) {
+        let mut query = self.world.query::<(Entity, &Classed)>();
+        let nodes = query
+            .iter(self.world)
+            .map(|(entity, classed)| {
+                let data = PrimitiveData::Node {
+                    style: classed.style.clone(),
+                    color: classed.color,
+                };
+                (entity, self.apply_class(data, Some(&classed.class)))
+            })
+            .collect::<Vec<_>>();
+        let scale = self.ui_scale();
+        for (entity, data) in nodes {
+            let material = self.material_for(Some(entity), &data);
+            if let PrimitiveData::Node { style, .. } = data {
+                let style = style.unwrap_or_else(default_node_style);
+                self.world
+                    .entity_mut(entity)
+                    .insert(scaled(&style, scale))
+                    .insert(Unscaled(style))
+                    .insert(material.unwrap_or_default());
+            }
+        }
+    }
+    /// Fills in the style and color a node leaves unset from its class, if the sheet has it.
+    fn apply_class(&self, data: PrimitiveData, class: Option<&str>) -> PrimitiveData {
+        let class = match class.and_then(|name| self.world.get_resource::<StyleSheet>()?.get(name))
+        {
+            Some(class) => class,
+            None => return data,
+        };
+        match data {
+            PrimitiveData::Node { style, color } => PrimitiveData::Node {
+                style: style.or_else(|| class.style.clone()),
+                color: color.or(class.color),
+            },
+            data => data,
+        }
+    }
+    fn ui_scale(&self) -> f32 {
+        self.world
+            .get_resource::<UiScale>()
+            .map_or(1., |scale| scale.0)
+    }
+    fn theme(&self) -> Theme {
+        self.world
+            .get_resource::<Theme>()
+            .cloned()
+            .unwrap_or_default()
+    }
+    /// The material `primitive` displays with. A material `existing` already displays with is
+    /// updated in place, so re-rendering a changing color doesn't add a material every time.
+    fn material_for(
+        &mut self,
+        existing: Option<Entity>,
+        primitive: &PrimitiveData,
+    ) -> Option<Handle<ColorMaterial>> {
+        let material = match primitive {
+            PrimitiveData::Node {
+                color: Some(color), ..
+            } => ColorMaterial::from(*color),
+            PrimitiveData::Image { texture, .. } => ColorMaterial::from(texture.clone()),
+            _ => return None,
+        };
+        // Only nodes and images made their material, raw primitives may share theirs.
+        let owned = existing.and_then(|entity| {
+            let entity = self.world.entity(entity);
+            match entity.get::<PrimitiveKind>()? {
+                PrimitiveKind::Node | PrimitiveKind::Image => entity
+                    .get::<Handle<ColorMaterial>>()
+                    .filter(|handle| **handle != Handle::default())
+                    .cloned(),
+                _ => None,
+            }
+        });
+        let mut materials = self
+            .world
+            .get_resource_mut::<Assets<ColorMaterial>>()
+            .unwrap();
+        if let Some(handle) = owned {
+            if let Some(current) = materials.get(&handle) {
+                if current.color != material.color || current.texture != material.texture {
+                    *materials.get_mut(&handle).unwrap() = material;
+                }
+                return Some(handle);
+            }
+        }
+        Some(materials.add(material))
+    }
 }
 
-fn helper(entity: &mut EntityMut, primitive: PrimitiveData, font: Handle<Font>) {
+fn helper(
+    entity: &mut EntityMut,
+    primitive: PrimitiveData,
+    font: Handle<Font>,
+    material: Option<Handle<ColorMaterial>>,
+    theme: &Theme,
+    scale: f32,
+) {
     let kind = match primitive {
-        PrimitiveData::Node => {
+        PrimitiveData::Node { style, .. } => {
+            let style = style.unwrap_or_else(default_node_style);
             entity.insert_bundle(NodeBundle {
-                style: Style {
-                    flex_direction: FlexDirection::ColumnReverse,
-                    align_items: AlignItems::FlexStart,
-                    ..Default::default()
-                },
+                style: scaled(&style, scale),
+                material: material.unwrap_or_default(),
                 ..Default::default()
             });
+            entity.insert(Unscaled(style));
             PrimitiveKind::Node
         }
-        PrimitiveData::Text(value) => {
+        PrimitiveData::Text { value, style } => {
             entity.insert_bundle(TextBundle {
                 text: Text::with_section(
                     value,
-                    TextStyle {
-                        font,
-                        font_size: 30.,
-                        color: Color::BLACK,
-                    },
+                    text_style(&style, font, theme, scale),
                     Default::default(),
                 ),
                 ..Default::default()
             });
+            entity.insert(Themed(style));
             PrimitiveKind::Text
         }
-        PrimitiveData::Image => {
+        PrimitiveData::Image { style, .. } => {
+            let style = style.unwrap_or_default();
             entity.insert_bundle(ImageBundle {
+                style: scaled(&style, scale),
+                material: material.unwrap_or_default(),
                 ..Default::default()
             });
+            entity.insert(Unscaled(style));
             PrimitiveKind::Image
         }
         PrimitiveData::Button => {
@@ -128,13 +850,561 @@ fn helper(entity: &mut EntityMut, primitive: PrimitiveData, font: Handle<Font>)
             });
             PrimitiveKind::Button
         }
+        PrimitiveData::TextInput { value, on_change } => {
+            // Wide enough to click on while empty.
+            let style = Style {
+                min_size: Size::new(Val::Px(100.), Val::Px(theme.font_size)),
+                ..Default::default()
+            };
+            entity.insert_bundle(TextBundle {
+                text: Text::with_section(
+                    value.clone(),
+                    text_style(&TextOverrides::default(), font, theme, scale),
+                    Default::default(),
+                ),
+                style: scaled(&style, scale),
+                ..Default::default()
+            });
+            entity.insert(Unscaled(style));
+            entity.insert(Themed(TextOverrides::default()));
+            entity.insert(Interaction::default());
+            entity.insert(FocusPolicy::Block);
+            entity.insert(TextInputState { value, on_change });
+            PrimitiveKind::TextInput
+        }
+        PrimitiveData::Raw(spawn) => {
+            (spawn.0)(entity);
+            PrimitiveKind::Raw
+        }
     };
     entity.insert(kind);
 }
 
+fn text_style(
+    overrides: &TextOverrides,
+    font: Handle<Font>,
+    theme: &Theme,
+    scale: f32,
+) -> TextStyle {
+    TextStyle {
+        font: overrides.font.clone().unwrap_or(font),
+        font_size: overrides.size.unwrap_or(theme.font_size) * scale,
+        color: overrides.color.unwrap_or(theme.text_color),
+    }
+}
+
+/// Multiplies every pixel dimension of `style` by `scale`.
+fn scaled(style: &Style, scale: f32) -> Style {
+    let val = |v: Val| match v {
+        Val::Px(px) => Val::Px(px * scale),
+        v => v,
+    };
+    let rect = |r: Rect<Val>| Rect {
+        left: val(r.left),
+        right: val(r.right),
+        top: val(r.top),
+        bottom: val(r.bottom),
+    };
+    let size = |s: Size<Val>| Size::new(val(s.width), val(s.height));
+    Style {
+        position: rect(style.position),
+        margin: rect(style.margin),
+        padding: rect(style.padding),
+        border: rect(style.border),
+        flex_basis: val(style.flex_basis),
+        size: size(style.size),
+        min_size: size(style.min_size),
+        max_size: size(style.max_size),
+        ..style.clone()
+    }
+}
+
+fn default_node_style() -> Style {
+    Style {
+        flex_direction: FlexDirection::ColumnReverse,
+        align_items: AlignItems::FlexStart,
+        ..Default::default()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PrimitiveKind {
     Node,
     Text,
     Image,
     Button,
+    TextInput,
+    Raw,
+}
+
+/// A mounted primitive and those below it, see [`Dom::dump_tree`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrimitiveNode {
+    pub kind: PrimitiveKind,
+    /// The value of text and text inputs.
+    pub text: Option<String>,
+    pub children: Vec<PrimitiveNode>,
+}
+
+impl PrimitiveNode {
+    fn of(world: &World, entity: Entity) -> Option<Self> {
+        let kind = *world.get::<PrimitiveKind>(entity)?;
+        let text = world.get::<Text>(entity).map(|text| {
+            text.sections
+                .iter()
+                .map(|section| section.value.as_str())
+                .collect()
+        });
+        let children = world
+            .get::<Children>(entity)
+            .map_or_else(Vec::new, |children| {
+                children
+                    .iter()
+                    .filter_map(|child| Self::of(world, *child))
+                    .collect()
+            });
+        Some(Self {
+            kind,
+            text,
+            children,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        app::Events,
+        prelude::{IntoSystem, Stage, SystemStage},
+        ui::{flex_node_system, FlexSurface},
+        window::{Window, WindowDescriptor, WindowId, WindowScaleFactorChanged, Windows},
+    };
+
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::prelude::{
+        e, Callback, ComponentFunc, Element, Fctx, HookedDiagnostics, TestHarness,
+    };
+
+    struct Textures(Handle<Texture>, Handle<Texture>);
+
+    fn swatch(ctx: Fctx) -> Element {
+        let (on, set_on) = ctx.use_state(|| false);
+        let textures = ctx.use_resource::<Textures>();
+        let (color, texture) = if *on {
+            (Color::RED, &textures.1)
+        } else {
+            (Color::BLUE, &textures.0)
+        };
+        e::node([
+            e::button([e::text("Toggle")]).on_click(move |_| set_on.set(|on| *on = !*on)),
+            e::divider(1., color),
+            e::image(texture.clone()),
+        ])
+    }
+
+    /// The material handles of the divider and the image, in that order.
+    fn materials(harness: &mut TestHarness) -> Vec<Handle<ColorMaterial>> {
+        let mut query = harness
+            .world
+            .query::<(&PrimitiveKind, &Handle<ColorMaterial>)>();
+        let mut handles = query
+            .iter(&harness.world)
+            .filter(|(_, handle)| **handle != Handle::default())
+            .map(|(kind, handle)| (*kind == PrimitiveKind::Image, handle.clone()))
+            .collect::<Vec<_>>();
+        handles.sort_by_key(|(image, _)| *image);
+        handles.into_iter().map(|(_, handle)| handle).collect()
+    }
+
+    #[test]
+    fn rerendering_updates_materials_in_place() {
+        let mut harness = TestHarness::new();
+        let mut textures = harness.world.get_resource_mut::<Assets<Texture>>().unwrap();
+        let textures = Textures(
+            textures.add(Texture::default()),
+            textures.add(Texture::default()),
+        );
+        let swapped = textures.1.clone();
+        harness.world.insert_resource(textures);
+        harness.mount(swatch.e(()));
+        let handles = materials(&mut harness);
+        assert_eq!(handles.len(), 2);
+        let count = harness
+            .world
+            .get_resource::<Assets<ColorMaterial>>()
+            .unwrap()
+            .len();
+        harness.click("Toggle");
+        assert_eq!(materials(&mut harness), handles);
+        let assets = harness
+            .world
+            .get_resource::<Assets<ColorMaterial>>()
+            .unwrap();
+        assert_eq!(assets.len(), count);
+        assert_eq!(assets.get(&handles[0]).unwrap().color, Color::RED);
+        assert_eq!(assets.get(&handles[1]).unwrap().texture, Some(swapped));
+    }
+
+    #[test]
+    fn backgrounds_fill_nodes() {
+        let mut harness = TestHarness::new();
+        harness.mount(e::node([e::text("Hi").background(Color::RED)]).background(Color::BLUE));
+        let handles = harness
+            .world
+            .query::<&Handle<ColorMaterial>>()
+            .iter(&harness.world)
+            .filter(|handle| **handle != Handle::default())
+            .cloned()
+            .collect::<Vec<_>>();
+        let assets = harness
+            .world
+            .get_resource::<Assets<ColorMaterial>>()
+            .unwrap();
+        assert_eq!(handles.len(), 1);
+        assert_eq!(assets.get(&handles[0]).unwrap().color, Color::BLUE);
+    }
+
+    #[test]
+    fn text_inputs_follow_the_theme() {
+        let mut harness = TestHarness::new();
+        harness.mount(e::text_input("Name", Callback::new(|_| {})));
+        harness.world.insert_resource(Theme {
+            text_color: Color::WHITE,
+            font_size: 20.,
+            ..Theme::default()
+        });
+        harness.tick();
+        let mut inputs = harness
+            .world
+            .query_filtered::<(&Text, &Style), With<TextInputState>>();
+        let (text, style) = inputs.iter(&harness.world).next().unwrap();
+        assert_eq!(text.sections[0].style.color, Color::WHITE);
+        assert_eq!(text.sections[0].style.font_size, 20.);
+        assert_eq!(style.min_size.height, Val::Px(20.));
+    }
+
+    struct Label(&'static str);
+
+    fn labeled(ctx: Fctx) -> Element {
+        let label = ctx.use_resource::<Label>();
+        e::button([e::text(label.0)]).on_click(|_| {})
+    }
+
+    #[test]
+    fn rerendering_a_button_keeps_its_interaction() {
+        let mut harness = TestHarness::new();
+        harness.world.insert_resource(Label("Save"));
+        harness.mount(labeled.e(()));
+        let mut buttons = harness
+            .world
+            .query_filtered::<&mut Interaction, With<ClickHandler>>();
+        *buttons.iter_mut(&mut harness.world).next().unwrap() = Interaction::Hovered;
+        harness.world.insert_resource(Label("Saved"));
+        harness.tick();
+        assert_eq!(harness.snapshot(), "Button\n  Text \"Saved\"\n");
+        assert_eq!(
+            *buttons.iter(&harness.world).next().unwrap(),
+            Interaction::Hovered
+        );
+    }
+
+    fn leaf(kind: PrimitiveKind, text: Option<&str>) -> PrimitiveNode {
+        PrimitiveNode {
+            kind,
+            text: text.map(String::from),
+            children: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn dumps_one_tree_per_root() {
+        let mut harness = TestHarness::new();
+        harness.mount(e::node([
+            e::text("Hello"),
+            e::button([e::text("Go")]).on_click(|_| {}),
+        ]));
+        harness.mount(e::text("Second"));
+        let tree = Dom {
+            world: &mut harness.world,
+        }
+        .dump_tree();
+        assert_eq!(
+            tree,
+            [
+                PrimitiveNode {
+                    kind: PrimitiveKind::Node,
+                    text: None,
+                    children: vec![
+                        leaf(PrimitiveKind::Text, Some("Hello")),
+                        PrimitiveNode {
+                            kind: PrimitiveKind::Button,
+                            text: None,
+                            children: vec![leaf(PrimitiveKind::Text, Some("Go"))],
+                        },
+                    ],
+                },
+                leaf(PrimitiveKind::Text, Some("Second")),
+            ],
+        );
+    }
+
+    fn shuffled(ctx: Fctx) -> Element {
+        let (items, set_items) = ctx.use_state(|| vec![1u64, 2, 3, 4, 5]);
+        e::node([
+            e::button([e::text("Shuffle")]).on_click(move |_| {
+                set_items.set(|items| {
+                    items.swap(0, 4);
+                    items.rotate_left(2);
+                })
+            }),
+            e::node(e::keyed_list(
+                items.iter().copied(),
+                |n| *n,
+                |n| e::text(n.to_string()),
+            )),
+        ])
+    }
+
+    /// The texts below `parent`, checking each one's `ChildIndex` is where it is.
+    fn indexed_texts(world: &World, parent: Entity) -> Vec<String> {
+        let children = world.get::<Children>(parent).unwrap();
+        children
+            .iter()
+            .enumerate()
+            .map(|(index, &child)| {
+                assert_eq!(world.get::<ChildIndex>(child).unwrap().0, index);
+                world.get::<Text>(child).unwrap().sections[0].value.clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn child_indices_follow_reorders_and_removals() {
+        let mut harness = TestHarness::new();
+        harness.mount(shuffled.e(()));
+        let (one, list) = harness
+            .world
+            .query::<(Entity, &Text, &Parent)>()
+            .iter(&harness.world)
+            .find(|(_, text, _)| text.sections[0].value == "1")
+            .map(|(entity, _, parent)| (entity, parent.0))
+            .unwrap();
+        assert_eq!(
+            indexed_texts(&harness.world, list),
+            ["1", "2", "3", "4", "5"]
+        );
+        harness.click("Shuffle");
+        assert_eq!(
+            indexed_texts(&harness.world, list),
+            ["3", "4", "1", "5", "2"]
+        );
+        Dom {
+            world: &mut harness.world,
+        }
+        .remove(PrimitiveId(one));
+        assert_eq!(indexed_texts(&harness.world, list), ["3", "4", "5", "2"]);
+    }
+
+    fn square() -> Element {
+        e::styled_node(
+            Style {
+                size: Size::new(Val::Px(10.), Val::Px(10.)),
+                ..Default::default()
+            },
+            Vec::new(),
+        )
+    }
+
+    /// Lays the mounted nodes out in an 800 by 600 window, the way `UiPlugin` does every frame.
+    fn lay_out(harness: &mut TestHarness) {
+        let mut windows = Windows::default();
+        windows.add(Window::new(
+            WindowId::primary(),
+            &WindowDescriptor::default(),
+            800,
+            600,
+            1.,
+            None,
+        ));
+        harness.world.insert_resource(windows);
+        harness
+            .world
+            .insert_resource(Events::<WindowScaleFactorChanged>::default());
+        harness.world.insert_resource(FlexSurface::default());
+        SystemStage::single(flex_node_system.system()).run(&mut harness.world);
+    }
+
+    /// Where the first of two squares laid out in `direction` ends up relative to the second.
+    fn first_square_offset(direction: FlexDirection) -> Vec2 {
+        let mut harness = TestHarness::new();
+        harness.mount(e::styled_node(
+            Style {
+                flex_direction: direction,
+                size: Size::new(Val::Px(100.), Val::Px(100.)),
+                ..Default::default()
+            },
+            vec![square(), square()],
+        ));
+        lay_out(&mut harness);
+        let squares = harness
+            .world
+            .query_filtered::<&Children, (With<PrimitiveKind>, Without<Parent>)>()
+            .iter(&harness.world)
+            .next()
+            .unwrap()
+            .to_vec();
+        let position = |entity| {
+            harness
+                .world
+                .get::<Transform>(entity)
+                .unwrap()
+                .translation
+                .truncate()
+        };
+        position(squares[0]) - position(squares[1])
+    }
+
+    #[test]
+    fn the_first_element_shows_up_where_the_flex_direction_starts() {
+        assert!(first_square_offset(FlexDirection::Row).x < 0.);
+        assert!(first_square_offset(FlexDirection::RowReverse).x > 0.);
+        // Bevy's UI y axis points up.
+        assert!(first_square_offset(FlexDirection::Column).y < 0.);
+        assert!(first_square_offset(FlexDirection::ColumnReverse).y > 0.);
+    }
+
+    fn nested(ctx: Fctx) -> Element {
+        let (shown, set_shown) = ctx.use_state(|| true);
+        e::node([
+            e::button([e::text("Toggle")])
+                .on_click(move |_| set_shown.set(|shown| *shown = !*shown)),
+            if *shown {
+                e::node([e::node([e::text("Deep")]), e::text("Shallow")])
+            } else {
+                e::text("Hidden")
+            },
+        ])
+    }
+
+    #[test]
+    fn toggling_nested_trees_keeps_every_parent() {
+        let mut harness = TestHarness::new();
+        harness.mount(nested.e(()));
+        for _ in 0..11 {
+            harness.click("Toggle");
+        }
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Button\n    Text \"Toggle\"\n  Text \"Hidden\"\n",
+        );
+    }
+
+    #[test]
+    fn mounting_under_a_despawned_parent_is_reported() {
+        let mut harness = TestHarness::new();
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let sink = reported.clone();
+        harness
+            .world
+            .insert_resource(HookedDiagnostics::new(move |diagnostic| {
+                sink.lock().unwrap().push(diagnostic)
+            }));
+        let parent = harness.world.spawn().id();
+        harness.world.despawn(parent);
+        let primitive = Primitive {
+            data: PrimitiveData::Node {
+                style: None,
+                color: None,
+            },
+            semantics: None,
+            class: None,
+            selectable: false,
+            transform: None,
+            scroll: None,
+            on_click: None,
+        };
+        let id = Dom {
+            world: &mut harness.world,
+        }
+        .mount_as_child(primitive, Some(PrimitiveId(parent)));
+        assert!(harness.world.get::<Parent>(id.0).is_none());
+        match &reported.lock().unwrap()[..] {
+            [Diagnostic::ParentDespawned {
+                parent: reported_parent,
+                primitive,
+            }] => assert_eq!((*reported_parent, *primitive), (parent, id.0)),
+            reported => panic!("expected a single ParentDespawned, got {:?}", reported),
+        }
+    }
+
+    fn warning(ctx: Fctx) -> Element {
+        let (urgent, set_urgent) = ctx.use_state(|| false);
+        let color = if *urgent { Color::RED } else { Color::YELLOW };
+        e::node([
+            e::button([e::text("Escalate")])
+                .on_click(move |_| set_urgent.set(|urgent| *urgent = true)),
+            e::styled_text("Careful", 18., color),
+        ])
+    }
+
+    /// The text primitive reading "Careful" and its style.
+    fn careful(harness: &mut TestHarness) -> (Entity, TextStyle) {
+        harness
+            .world
+            .query::<(Entity, &Text)>()
+            .iter(&harness.world)
+            .find(|(_, text)| text.sections[0].value == "Careful")
+            .map(|(entity, text)| (entity, text.sections[0].style.clone()))
+            .unwrap()
+    }
+
+    #[test]
+    fn restyled_text_updates_in_place() {
+        let mut harness = TestHarness::new();
+        harness.mount(warning.e(()));
+        let (entity, style) = careful(&mut harness);
+        assert_eq!((style.color, style.font_size), (Color::YELLOW, 18.));
+        harness.click("Escalate");
+        let (restyled, style) = careful(&mut harness);
+        assert_eq!(restyled, entity);
+        assert_eq!((style.color, style.font_size), (Color::RED, 18.));
+    }
+
+    struct Marker(u32);
+
+    fn marked(ctx: Fctx) -> Element {
+        let (n, set_n) = ctx.use_state(|| 1);
+        let n = *n;
+        e::node([
+            e::button([e::text("Bump")]).on_click(move |_| set_n.set(|n| *n += 1)),
+            e::raw(
+                move |entity| {
+                    entity
+                        .insert_bundle(NodeBundle::default())
+                        .insert(Marker(n));
+                },
+                Vec::new(),
+            ),
+        ])
+    }
+
+    fn markers(harness: &mut TestHarness) -> Vec<(PrimitiveKind, u32)> {
+        harness
+            .world
+            .query::<(&PrimitiveKind, &Marker)>()
+            .iter(&harness.world)
+            .map(|(kind, marker)| (*kind, marker.0))
+            .collect()
+    }
+
+    #[test]
+    fn raw_primitives_insert_their_bundles() {
+        let mut harness = TestHarness::new();
+        harness.mount(marked.e(()));
+        assert_eq!(markers(&mut harness), [(PrimitiveKind::Raw, 1)]);
+        harness.click("Bump");
+        assert_eq!(markers(&mut harness), [(PrimitiveKind::Raw, 2)]);
+    }
 }
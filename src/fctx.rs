@@ -1,17 +1,59 @@
-use std::{any::TypeId, cell::RefCell, marker::PhantomData, ops::Deref, sync::Arc};
+use std::{
+    any::{Any, TypeId},
+    cell::{Cell, RefCell},
+    future::Future,
+    marker::PhantomData,
+    mem,
+    ops::Deref,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use bevy::{ecs::component::Component, prelude::*, utils::HashMap};
+use bevy::{
+    ecs::component::Component,
+    prelude::*,
+    reflect::Reflect,
+    tasks::{AsyncComputeTaskPool, Task},
+    utils::{HashMap, HashSet},
+};
 
-use crate::internal::{EffectResolver, MountedId, Tx};
+use futures_lite::future;
+
+use crate::{
+    diagnostics::{report, Diagnostic},
+    dom::PrimitiveId,
+    focus::focus_within,
+    internal::{
+        find_inherited, first_primitive, shared_element, Children, CmpCheck, EffectResolver,
+        Element, Key, MountedId, ResCheck, Tx,
+    },
+    measure::TextMeasureCache,
+    persist::{PersistedKeys, PersistedState},
+    scroll::{ScrollController, ScrollState},
+    shortcuts::{ActionId, KeyCombo, ShortcutRegistry},
+    FontHandle,
+};
 
 pub struct Fctx<'a> {
     tx: Tx,
     id: MountedId,
-    res_checks: Option<RefCell<&'a mut HashMap<TypeId, (fn(&World) -> bool, Vec<MountedId>)>>>,
-    cmp_checks: Option<RefCell<&'a mut HashMap<MountedId, Vec<fn(&mut World, MountedId) -> bool>>>>,
+    res_checks: Option<RefCell<&'a mut HashMap<TypeId, ResCheck>>>,
+    cmp_checks: Option<RefCell<&'a mut HashMap<MountedId, Vec<CmpCheck>>>>,
     init: bool,
     world: &'a mut World,
     nonsend_queue: RefCell<Vec<Box<dyn FnOnce(&mut World)>>>,
+    discard: bool,
+    slots: RefCell<&'a mut Vec<Box<dyn HookSlot>>>,
+    slot_cursor: Cell<usize>,
+    prev_children: Option<&'a Children>,
+}
+
+/// Hook state that isn't a Bevy component, stored per mounted component in call order.
+pub(crate) trait HookSlot {
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    /// Runs when the owning component unmounts.
+    fn teardown(self: Box<Self>, world: &mut World);
 }
 
 impl<'a> Fctx<'a> {
@@ -19,8 +61,9 @@ impl<'a> Fctx<'a> {
     pub(crate) fn render_first(
         tx: Tx,
         id: MountedId,
-        res_checks: &'a mut HashMap<TypeId, (fn(&World) -> bool, Vec<MountedId>)>,
-        cmp_checks: &'a mut HashMap<MountedId, Vec<fn(&mut World, MountedId) -> bool>>,
+        res_checks: &'a mut HashMap<TypeId, ResCheck>,
+        cmp_checks: &'a mut HashMap<MountedId, Vec<CmpCheck>>,
+        slots: &'a mut Vec<Box<dyn HookSlot>>,
         world: &'a mut World,
     ) -> Self {
         Self {
@@ -31,10 +74,20 @@ impl<'a> Fctx<'a> {
             init: true,
             world,
             nonsend_queue: RefCell::default(),
+            discard: false,
+            slots: RefCell::new(slots),
+            slot_cursor: Cell::new(0),
+            prev_children: None,
         }
     }
 
-    pub(crate) fn update(tx: Tx, id: MountedId, world: &'a mut World) -> Self {
+    pub(crate) fn update(
+        tx: Tx,
+        id: MountedId,
+        slots: &'a mut Vec<Box<dyn HookSlot>>,
+        prev_children: &'a Children,
+        world: &'a mut World,
+    ) -> Self {
         Self {
             tx,
             id,
@@ -43,19 +96,161 @@ impl<'a> Fctx<'a> {
             cmp_checks: None,
             world,
             nonsend_queue: RefCell::default(),
+            discard: false,
+            slots: RefCell::new(slots),
+            slot_cursor: Cell::new(0),
+            prev_children: Some(prev_children),
+        }
+    }
+
+    /// Makes this context drop its queued world mutations instead of applying them.
+    pub(crate) fn discarding_effects(mut self) -> Self {
+        self.discard = true;
+        self
+    }
+
+    fn use_slot<S: HookSlot + 'static, R>(
+        &self,
+        init: impl FnOnce() -> S,
+        f: impl FnOnce(&mut S) -> R,
+    ) -> R {
+        let index = self.slot_cursor.get();
+        self.slot_cursor.set(index + 1);
+        let mut slots = self.slots.borrow_mut();
+        if index == slots.len() {
+            slots.push(Box::new(init()));
+        }
+        let slot = slots[index]
+            .as_any_mut()
+            .downcast_mut::<S>()
+            .expect("hooks must be called in the same order on every render");
+        f(slot)
+    }
+
+    /// Plain per-component storage without any teardown.
+    pub(crate) fn use_plain<T: 'static, R>(
+        &self,
+        init: impl FnOnce() -> T,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> R {
+        self.use_slot(|| Plain(init()), |slot| f(&mut slot.0))
+    }
+
+    /// Storage for the state of a [`StatefulComponent`](crate::prelude::StatefulComponent),
+    /// empty until the component fills it on its first render.
+    pub(crate) fn use_stateful<S: 'static>(
+        &self,
+        on_unmount: fn(S, &mut World),
+    ) -> Rc<RefCell<Option<S>>> {
+        self.use_slot(
+            || Stateful {
+                state: Rc::new(RefCell::new(None)),
+                on_unmount,
+            },
+            |slot| slot.state.clone(),
+        )
+    }
+
+    /// Per-call state of a hook driven by a system, kept in the [`HookStates`] on the component's
+    /// entity so the system can query it. The first render queues inserting what `init` returns
+    /// and registers `changed`, which decides whether the state calls for a re-render.
+    /// Returns the index of this call's state.
+    fn use_hook_state<S: Component>(
+        &self,
+        init: impl FnOnce(&World) -> S + 'static,
+        changed: fn(&mut S) -> bool,
+    ) -> usize {
+        let earlier = if self.init {
+            self.slots
+                .borrow_mut()
+                .iter_mut()
+                .filter(|slot| slot.as_any_mut().is::<Plain<StateIndex<S>>>())
+                .count()
+        } else {
+            0
+        };
+        let index = self.use_plain(|| StateIndex::<S>(earlier, PhantomData), |index| index.0);
+        if self.init {
+            let entity = self.id.0;
+            self.nonsend_queue.borrow_mut().push(Box::new(move |world| {
+                let state = init(world);
+                let mut entity = world.entity_mut(entity);
+                match entity.get_mut::<HookStates<S>>() {
+                    Some(mut states) => states.0.push(state),
+                    None => {
+                        entity.insert(HookStates(vec![state]));
+                    }
+                }
+            }));
+            self.cmp_checks
+                .as_ref()
+                .unwrap()
+                .borrow_mut()
+                .entry(self.id)
+                .or_default()
+                .push(Box::new(move |world, e| {
+                    // Nothing to compare until the state queued by the first render is inserted.
+                    world
+                        .get_mut::<HookStates<S>>(e.0)
+                        .map_or(false, |mut states| {
+                            states.0.get_mut(index).map_or(false, changed)
+                        })
+                }));
         }
+        index
+    }
+
+    /// The state of a [`Fctx::use_hook_state`] call, `None` on the first render.
+    fn hook_state<S: Component>(&self, index: usize) -> Option<&S> {
+        self.world
+            .get::<HookStates<S>>(self.id.0)
+            .and_then(|states| states.0.get(index))
+    }
+
+    /// Queues `f` to update the state of a [`Fctx::use_hook_state`] call.
+    fn update_hook_state<S: Component>(&self, index: usize, f: impl FnOnce(&mut S) + 'static) {
+        let entity = self.id.0;
+        self.nonsend_queue.borrow_mut().push(Box::new(move |world| {
+            f(&mut world.get_mut::<HookStates<S>>(entity).unwrap().0[index])
+        }));
     }
 
     // User facing hooks
     pub fn use_resource<T: Component>(&self) -> &T {
+        self.try_use_resource().unwrap_or_else(|| {
+            panic!(
+                "use_resource: the resource `{}` doesn't exist",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+
+    /// Like [`Fctx::use_resource`], but returns `None` while the resource doesn't exist, e.g.
+    /// before the system inserting it ran. The component re-renders once it is inserted.
+    pub fn try_use_resource<T: Component>(&self) -> Option<&T> {
         if let Some(c) = &self.res_checks {
             c.borrow_mut()
                 .entry(std::any::TypeId::of::<T>())
-                .or_insert_with(|| (World::is_resource_changed::<T>, Vec::new()))
+                .or_insert_with(|| (resource_changed::<T>, HashSet::default()))
+                .1
+                .insert(self.id);
+        }
+        self.world.get_resource()
+    }
+
+    /// Returns the seconds elapsed since the last frame.
+    ///
+    /// This opts the component into rendering on every frame, whether or not anything else
+    /// changed, so keep the component calling it small.
+    pub fn use_delta_time(&self) -> f32 {
+        if let Some(c) = &self.res_checks {
+            c.borrow_mut()
+                .entry(TypeId::of::<EveryFrame>())
+                .or_insert_with(|| (every_frame, HashSet::default()))
                 .1
-                .push(self.id);
+                .insert(self.id);
         }
-        self.world.get_resource().unwrap()
+        self.world.get_resource::<Time>().unwrap().delta_seconds()
     }
 
     pub fn use_resource_setter<T: Component>(&self) -> Setter<T> {
@@ -66,31 +261,57 @@ impl<'a> Fctx<'a> {
         }
     }
 
+    /// State stored as a component on the mounted entity, so user systems can query it.
+    ///
+    /// `f` makes the initial value. Should a clone of the [`Ref::Rc`] it's first returned in be
+    /// kept past the render, the value can't be moved into the world. That's reported as a
+    /// [`Diagnostic::ValueStillShared`] and the state is made again on the next render.
     pub fn use_linked_state<T: Component, F: FnOnce() -> T>(
         &self,
         f: F,
     ) -> (Ref<'_, T>, Setter<T>) {
+        let existing = if self.init {
+            None
+        } else {
+            self.world.entity(self.id.0).get::<T>()
+        };
         (
-            if self.init {
-                let rc = Arc::new(f());
-                let entity = self.id.0;
-                let rc_clone = rc.clone();
-                self.nonsend_queue.borrow_mut().push(Box::new(move |world| {
-                    world
-                        .entity_mut(entity)
-                        .insert(Arc::try_unwrap(rc_clone).ok().unwrap());
-                }));
-                self.cmp_checks
-                    .as_ref()
-                    .unwrap()
-                    .borrow_mut()
-                    .entry(self.id)
-                    .or_default()
-                    .push(|world, e| world.entity_mut(e.0).get_mut::<T>().unwrap().is_changed());
-                Ref::Rc(rc)
-            } else {
-                let val = self.world.entity(self.id.0).get::<T>().unwrap();
-                Ref::Borrowed(val)
+            match existing {
+                Some(val) => Ref::Borrowed(val),
+                None => {
+                    let rc = Arc::new(f());
+                    let entity = self.id.0;
+                    let rc_clone = rc.clone();
+                    self.nonsend_queue.borrow_mut().push(Box::new(
+                        move |world| match Arc::try_unwrap(rc_clone) {
+                            Ok(value) => {
+                                world.entity_mut(entity).insert(value);
+                            }
+                            Err(_) => report(
+                                world,
+                                Diagnostic::ValueStillShared {
+                                    hook: "use_linked_state",
+                                    value: std::any::type_name::<T>(),
+                                },
+                            ),
+                        },
+                    ));
+                    // Made again after a `ValueStillShared`, the state is still watched by the
+                    // check registered when the component mounted.
+                    if let Some(checks) = self.cmp_checks.as_ref().filter(|_| self.init) {
+                        checks
+                            .borrow_mut()
+                            .entry(self.id)
+                            .or_default()
+                            .push(Box::new(|world, e| {
+                                world
+                                    .entity_mut(e.0)
+                                    .get_mut::<T>()
+                                    .map_or(false, |state| state.is_changed())
+                            }));
+                    }
+                    Ref::Rc(rc)
+                }
             },
             Setter {
                 tx: self.tx.clone(),
@@ -100,6 +321,383 @@ impl<'a> Fctx<'a> {
         )
     }
 
+    /// Like [`Fctx::use_linked_state`], but the state is kept private to the component instead
+    /// of being inserted into the world, so user systems can't query it. A value still read
+    /// by an earlier render is cloned rather than updated in place when set.
+    ///
+    /// The setter is a [`StateSetter`], as a [`Setter`] finds its state in the world. Setters
+    /// are called from click handlers and systems, so `T` is `Send + Sync` to get there, and
+    /// `Clone` for the copy made while a render still reads it.
+    pub fn use_state<T: Clone + Send + Sync + 'static>(
+        &self,
+        init: impl FnOnce() -> T,
+    ) -> (Ref<'_, T>, StateSetter<T>) {
+        let state = self.use_plain(|| Arc::new(Mutex::new(Arc::new(init()))), |s| s.clone());
+        let value = state.lock().unwrap().clone();
+        (
+            Ref::Rc(value),
+            StateSetter {
+                tx: self.tx.clone(),
+                id: self.id,
+                state,
+            },
+        )
+    }
+
+    /// Like [`Fctx::use_linked_state`], but included in [`PersistedState::capture`] snapshots
+    /// under `key`, and seeded from the [`PersistedState`] resource if it holds a value for
+    /// `key` when the component mounts.
+    ///
+    /// The tree has no identity that survives restarts, so `key` is what ties a saved value to
+    /// its component. It must be unique among mounted components and stay the same across runs,
+    /// e.g. derived from the keys of the elements above.
+    pub fn use_persisted_state<T: Component + Reflect>(
+        &self,
+        key: impl Into<String>,
+        init: impl FnOnce() -> T,
+    ) -> (Ref<'_, T>, Setter<T>) {
+        let key = key.into();
+        if self.init {
+            let entity = self.id.0;
+            let key = key.clone();
+            self.nonsend_queue.borrow_mut().push(Box::new(move |world| {
+                let mut entity = world.entity_mut(entity);
+                if !entity.contains::<PersistedKeys>() {
+                    entity.insert(PersistedKeys::default());
+                }
+                entity
+                    .get_mut::<PersistedKeys>()
+                    .unwrap()
+                    .0
+                    .push((key, |world, e| {
+                        world.get::<T>(e).map(|value| value.clone_value())
+                    }));
+            }));
+        }
+        self.use_linked_state(|| {
+            let mut value = init();
+            let saved = self
+                .world
+                .get_resource::<PersistedState>()
+                .and_then(|persisted| persisted.values.get(&key));
+            if let Some(saved) = saved {
+                value.apply(&**saved);
+            }
+            value
+        })
+    }
+
+    /// Counts the `period`s that passed since the component mounted, re-rendering on every new
+    /// tick. Handy for animations, e.g. a spinner showing `frames[ticks as usize % frames.len()]`.
+    ///
+    /// Changing `period` keeps the count reached so far.
+    pub fn use_interval_counter(&self, period: Duration) -> (u32, IntervalReset) {
+        let period = period.as_secs_f64();
+        let index = self.use_hook_state(
+            move |world| IntervalCounter {
+                origin: world
+                    .get_resource::<Time>()
+                    .unwrap()
+                    .seconds_since_startup(),
+                period,
+                ticks: 0,
+                fresh: false,
+            },
+            |counter: &mut IntervalCounter| mem::take(&mut counter.fresh),
+        );
+        let ticks = match self.hook_state::<IntervalCounter>(index) {
+            Some(counter) => {
+                if counter.period != period {
+                    self.update_hook_state(index, move |counter: &mut IntervalCounter| {
+                        counter.origin += counter.ticks as f64 * (counter.period - period);
+                        counter.period = period;
+                    });
+                }
+                counter.ticks
+            }
+            None => 0,
+        };
+        (
+            ticks,
+            IntervalReset {
+                tx: self.tx.clone(),
+                id: self.id,
+                index,
+            },
+        )
+    }
+
+    /// Runs a repeating timer of `duration` seconds, returning `true` on the render its elapsing
+    /// triggers and `false` on any other. Changing `duration` keeps the time elapsed so far.
+    pub fn use_timer(&self, duration: f32) -> (bool, TimerControls) {
+        let index = self.use_hook_state(
+            move |_| HookTimer {
+                timer: Timer::from_seconds(duration, true),
+                fired: false,
+            },
+            |timer: &mut HookTimer| timer.fired,
+        );
+        let fired = match self.hook_state::<HookTimer>(index) {
+            Some(timer) => {
+                let fired = timer.fired;
+                let retime = timer.timer.duration().as_secs_f32() != duration;
+                if fired || retime {
+                    self.update_hook_state(index, move |timer: &mut HookTimer| {
+                        timer.fired = false;
+                        if retime {
+                            timer.timer.set_duration(Duration::from_secs_f32(duration));
+                        }
+                    });
+                }
+                fired
+            }
+            None => false,
+        };
+        (
+            fired,
+            TimerControls {
+                tx: self.tx.clone(),
+                id: self.id,
+                index,
+            },
+        )
+    }
+
+    /// Runs `fut` on the [`AsyncComputeTaskPool`], re-rendering once it completes. Only the
+    /// future passed on the first render runs, later ones are dropped unpolled. Unmounting the
+    /// component cancels the task.
+    pub fn use_async<T, F>(&self, fut: F) -> AsyncState<T>
+    where
+        T: Clone + Send + Sync + 'static,
+        F: Future<Output = T> + Send + 'static,
+    {
+        let index = self.use_hook_state(
+            move |world| AsyncTask {
+                task: Some(
+                    world
+                        .get_resource::<AsyncComputeTaskPool>()
+                        .unwrap()
+                        .spawn(async move { Box::new(fut.await) as Box<dyn Any + Send + Sync> }),
+                ),
+                output: None,
+                fresh: false,
+            },
+            |task: &mut AsyncTask| task.fresh,
+        );
+        let task = match self.hook_state::<AsyncTask>(index) {
+            Some(task) => task,
+            None => return AsyncState::Pending,
+        };
+        if task.fresh {
+            self.update_hook_state(index, |task: &mut AsyncTask| task.fresh = false);
+        }
+        match &task.output {
+            Some(output) => AsyncState::Ready(output.downcast_ref::<T>().unwrap().clone()),
+            None => AsyncState::Pending,
+        }
+    }
+
+    /// Whether the cursor has been over `primitive` for at least `delay` without leaving, e.g.
+    /// for showing a tooltip. Turns false as soon as the cursor leaves.
+    pub fn use_hover_delay(&self, primitive: PrimitiveId, delay: Duration) -> bool {
+        let primitive = primitive.entity();
+        let delay = delay.as_secs_f64();
+        let index = self.use_hook_state(
+            move |_| HoverDelay {
+                primitive,
+                delay,
+                since: None,
+                shown: false,
+                rendered: false,
+            },
+            |hover: &mut HoverDelay| {
+                if hover.shown == hover.rendered {
+                    return false;
+                }
+                hover.rendered = hover.shown;
+                true
+            },
+        );
+        match self.hook_state::<HoverDelay>(index) {
+            Some(hover) => {
+                if hover.primitive != primitive || hover.delay != delay {
+                    self.update_hook_state(index, move |hover: &mut HoverDelay| {
+                        if hover.primitive != primitive {
+                            hover.primitive = primitive;
+                            hover.since = None;
+                            hover.shown = false;
+                        }
+                        hover.delay = delay;
+                    });
+                }
+                hover.shown
+            }
+            None => false,
+        }
+    }
+
+    /// Returns a value following `target` with spring physics, re-rendering every frame until it
+    /// settles. It starts out at `target`; later changes redirect the spring without snapping.
+    pub fn use_spring(&self, target: f32, config: SpringConfig) -> f32 {
+        let index = self.use_hook_state(
+            move |_| Spring {
+                value: target,
+                velocity: 0.,
+                target,
+                config,
+                moved: false,
+            },
+            |spring: &mut Spring| mem::take(&mut spring.moved),
+        );
+        match self.hook_state::<Spring>(index) {
+            Some(spring) => {
+                if spring.target != target || spring.config != config {
+                    self.update_hook_state(index, move |spring: &mut Spring| {
+                        spring.target = target;
+                        spring.config = config;
+                    });
+                }
+                spring.value
+            }
+            None => target,
+        }
+    }
+
+    /// Returns `compute(world)`, re-rendering only when its result changes. The general form of
+    /// hooks deriving something from the world, e.g. watching a set of entities with
+    /// `ctx.use_condition(entities_with::<Enemy>)`.
+    ///
+    /// `compute` runs on every `process_messages`, so keep it cheap.
+    pub fn use_condition<D: Component + Clone + PartialEq>(&self, compute: fn(&World) -> D) -> D {
+        let last = self.use_plain(
+            || Arc::new(Mutex::new(compute(self.world))),
+            |last| last.clone(),
+        );
+        if self.init {
+            let watched = last.clone();
+            self.cmp_checks
+                .as_ref()
+                .unwrap()
+                .borrow_mut()
+                .entry(self.id)
+                .or_default()
+                .push(Box::new(move |world, _| {
+                    let value = compute(world);
+                    let mut last = watched.lock().unwrap();
+                    if *last == value {
+                        return false;
+                    }
+                    *last = value;
+                    true
+                }));
+        }
+        let value = last.lock().unwrap().clone();
+        value
+    }
+
+    /// Returns `query(world)`, re-rendering only when its result changes. Unlike
+    /// [`Fctx::use_condition`], `query` can capture values, like the entity to read a component
+    /// off. The query captured by the latest render is the one watched.
+    ///
+    /// `query` runs on every `process_messages` for as long as the component is mounted, so
+    /// keep it cheap: a few component lookups are fine, iterating over every entity adds up.
+    pub fn use_world_query<T, F>(&self, query: F) -> T
+    where
+        T: Component + Clone + PartialEq,
+        F: Fn(&World) -> T + Send + Sync + 'static,
+    {
+        let value = query(self.world);
+        let watched = self.use_plain(
+            || Arc::new(Mutex::new(None)),
+            |watched: &mut Arc<Mutex<Option<WorldQuery<F, T>>>>| watched.clone(),
+        );
+        *watched.lock().unwrap() = Some(WorldQuery {
+            query,
+            last: value.clone(),
+        });
+        if self.init {
+            self.cmp_checks
+                .as_ref()
+                .unwrap()
+                .borrow_mut()
+                .entry(self.id)
+                .or_default()
+                .push(Box::new(move |world, _| {
+                    let mut watched = watched.lock().unwrap();
+                    let watched = watched.as_mut().unwrap();
+                    let value = (watched.query)(world);
+                    if watched.last == value {
+                        return false;
+                    }
+                    watched.last = value;
+                    true
+                }));
+        }
+        value
+    }
+
+    /// Whether the [`Focused`](crate::prelude::Focused) entity is `primitive` or one of its
+    /// descendants, re-rendering when focus moves in or out of it.
+    pub fn use_focus_within(&self, primitive: PrimitiveId) -> bool {
+        let primitive = primitive.entity();
+        let inside = focus_within(self.world, primitive);
+        let watched = self.use_plain(
+            || Arc::new(Mutex::new(FocusWithin { primitive, inside })),
+            |watched| watched.clone(),
+        );
+        *watched.lock().unwrap() = FocusWithin { primitive, inside };
+        if self.init {
+            self.cmp_checks
+                .as_ref()
+                .unwrap()
+                .borrow_mut()
+                .entry(self.id)
+                .or_default()
+                .push(Box::new(move |world, _| {
+                    let mut watched = watched.lock().unwrap();
+                    let inside = focus_within(world, watched.primitive);
+                    if inside == watched.inside {
+                        return false;
+                    }
+                    watched.inside = inside;
+                    true
+                }));
+        }
+        inside
+    }
+
+    /// Whether the cursor is over the primitive this component rendered, re-rendering when it
+    /// enters or leaves. Only primitives with an [`Interaction`], like buttons, report hovering.
+    /// The primitive mounts after the first render, which therefore always sees `false`.
+    pub fn use_hover(&self) -> bool {
+        let hover = self.use_plain(|| Arc::new(Mutex::new(false)), |hover| hover.clone());
+        if self.init {
+            let watched = hover.clone();
+            self.cmp_checks
+                .as_ref()
+                .unwrap()
+                .borrow_mut()
+                .entry(self.id)
+                .or_default()
+                .push(Box::new(move |world, e| {
+                    let hovered = first_primitive(world, e)
+                        .and_then(|p| world.get::<Interaction>(p.entity()))
+                        .map_or(false, |i| *i != Interaction::None);
+                    let mut hover = watched.lock().unwrap();
+                    if *hover == hovered {
+                        return false;
+                    }
+                    *hover = hovered;
+                    true
+                }));
+        }
+        let hovered = *hover.lock().unwrap();
+        hovered
+    }
+
+    /// Shares `v` with every component below this one, which read it with
+    /// [`Fctx::use_inherited_state`]. It is in place before the children render, including
+    /// their first render.
     pub fn use_broadcast_state<T: Component>(&self, v: T) {
         let entity = self.id.0;
         self.nonsend_queue.borrow_mut().push(Box::new(move |world| {
@@ -107,6 +705,184 @@ impl<'a> Fctx<'a> {
         }));
     }
 
+    /// Runs `handler` whenever `combo` is pressed, for as long as the component is mounted.
+    /// Registered shortcuts are listed in the [`ShortcutRegistry`] resource.
+    pub fn use_shortcut(
+        &self,
+        combo: KeyCombo,
+        action: ActionId,
+        handler: impl Fn() + Send + Sync + 'static,
+    ) {
+        let id = self.id;
+        self.use_slot(|| ShortcutOwner(id), |_| ());
+        self.nonsend_queue.borrow_mut().push(Box::new(move |world| {
+            world
+                .get_resource_mut::<ShortcutRegistry>()
+                .unwrap()
+                .register(id, combo, action, Box::new(handler));
+        }));
+    }
+
+    /// Tells the closest [`suspense`](crate::prelude::e::suspense) boundary above whether this
+    /// component is still waiting on something. The boundary shows its fallback while any
+    /// component below it is pending. Does nothing outside of a boundary.
+    pub fn use_suspense(&self, pending: bool) {
+        let boundary = find_inherited::<SuspenseBoundary>(self.world, self.id);
+        let id = self.id;
+        let changed = self.use_slot(
+            || Suspender {
+                boundary,
+                id,
+                pending: false,
+            },
+            |slot| {
+                let changed = slot.pending != pending;
+                slot.boundary = boundary;
+                slot.pending = pending;
+                changed
+            },
+        );
+        if let (true, Some(boundary)) = (changed, boundary) {
+            self.nonsend_queue.borrow_mut().push(Box::new(move |world| {
+                let mut suspense = world.get_mut::<SuspenseBoundary>(boundary).unwrap();
+                if pending {
+                    suspense.pending.insert(id);
+                } else {
+                    suspense.pending.remove(&id);
+                }
+            }));
+        }
+    }
+
+    /// Validates `value` with `rules`, returning the current error. The rules only run again
+    /// when `value` changes. The result is reported to the closest form above, see
+    /// [`Fctx::use_form`].
+    pub fn use_validation<T: Clone + PartialEq + 'static>(
+        &self,
+        value: T,
+        rules: impl Fn(&T) -> Result<(), String>,
+    ) -> Option<String> {
+        let form = find_inherited::<FormState>(self.world, self.id);
+        let id = self.id;
+        let (error, changed) = self.use_slot(
+            || Validation::<T> {
+                form,
+                id,
+                checked: None,
+                invalid: false,
+            },
+            |slot| {
+                slot.form = form;
+                if slot.checked.as_ref().map_or(true, |(old, _)| *old != value) {
+                    let error = rules(&value).err();
+                    slot.checked = Some((value, error));
+                }
+                let error = slot.checked.as_ref().unwrap().1.clone();
+                let changed = slot.invalid != error.is_some();
+                slot.invalid = error.is_some();
+                (error, changed)
+            },
+        );
+        if let (true, Some(form)) = (changed, form) {
+            let invalid = error.is_some();
+            self.nonsend_queue.borrow_mut().push(Box::new(move |world| {
+                let mut state = world.get_mut::<FormState>(form).unwrap();
+                if invalid {
+                    state.invalid.insert(id);
+                } else {
+                    state.invalid.remove(&id);
+                }
+            }));
+        }
+        error
+    }
+
+    /// Makes this component a form, returning whether every field below it checked with
+    /// [`Fctx::use_validation`] is currently valid, e.g. to disable a submit button.
+    pub fn use_form(&self) -> bool {
+        let (form, _) = self.use_linked_state(FormState::default);
+        form.invalid.is_empty()
+    }
+
+    /// Reads the `T` broadcast by the closest component above this one with
+    /// [`Fctx::use_broadcast_state`], re-rendering whenever it changes.
+    pub fn use_inherited_state<T: Component>(&self) -> Option<&T> {
+        if let Some(c) = &self.cmp_checks {
+            c.borrow_mut().entry(self.id).or_default().push(Box::new(
+                |world, e| match find_inherited::<T>(world, e) {
+                    Some(ancestor) => world
+                        .entity_mut(ancestor)
+                        .get_mut::<T>()
+                        .unwrap()
+                        .is_changed(),
+                    None => false,
+                },
+            ));
+        }
+        find_inherited::<T>(self.world, self.id).and_then(|ancestor| self.world.get(ancestor))
+    }
+
+    /// Provides `value` to every component below this one, which read it with
+    /// [`Fctx::use_context`]. Unlike [`Fctx::use_broadcast_state`], it doesn't clash with state
+    /// of the same type linked to this component.
+    pub fn use_provide_context<T: Send + Sync + 'static>(&self, value: T) {
+        self.use_broadcast_state(Provided(value));
+    }
+
+    /// Reads the `T` provided by the closest component above this one with
+    /// [`Fctx::use_provide_context`], re-rendering when the provided value changes. Providing an
+    /// equal value again doesn't re-render consumers, even memoized ones.
+    pub fn use_context<T: Clone + PartialEq + Send + Sync + 'static>(&self) -> Option<Ref<'_, T>> {
+        if let Some(c) = &self.cmp_checks {
+            let mut last = provided::<T>(self.world, self.id).cloned();
+            c.borrow_mut()
+                .entry(self.id)
+                .or_default()
+                .push(Box::new(move |world, e| {
+                    let value = provided::<T>(world, e);
+                    if value == last.as_ref() {
+                        return false;
+                    }
+                    last = value.cloned();
+                    true
+                }));
+        }
+        provided(self.world, self.id).map(Ref::Borrowed)
+    }
+
+    /// Runs `effect` once this render finishes if `deps` changed since the last render, which
+    /// includes the first one. The cleanup it returns runs before the effect runs again, and
+    /// when the component unmounts, after the cleanups of its children and before its linked
+    /// state is despawned.
+    pub fn use_effect<D: PartialEq + 'static>(
+        &self,
+        deps: D,
+        effect: impl FnOnce() -> Cleanup + 'static,
+    ) {
+        let cleanup = self.use_slot(
+            || Effect::<D> {
+                deps: None,
+                cleanup: Rc::default(),
+            },
+            |slot| {
+                if slot.deps.as_ref() == Some(&deps) {
+                    return None;
+                }
+                slot.deps = Some(deps);
+                Some(slot.cleanup.clone())
+            },
+        );
+        if let Some(cleanup) = cleanup {
+            self.nonsend_queue.borrow_mut().push(Box::new(move |world| {
+                let old = cleanup.borrow_mut().take();
+                if let Some(old) = old {
+                    old(world);
+                }
+                *cleanup.borrow_mut() = Some(effect());
+            }));
+        }
+    }
+
     pub fn use_disconnected_state<T: Component, F: FnOnce() -> T>(&self, f: F) {
         if self.init {
             let v = f();
@@ -120,54 +896,1417 @@ impl<'a> Fctx<'a> {
     pub fn use_self(&self) -> Entity {
         self.id.0
     }
+
+    /// Returns the keys of the children this component rendered last time, in order, with `None`
+    /// for unkeyed ones. Empty on the first render. Useful for computing enter and leave sets.
+    pub fn use_prev_children(&self) -> Vec<Option<Key>> {
+        self.prev_children
+            .map(|children| children.keys().collect())
+            .unwrap_or_default()
+    }
+
+    /// Collects the updates made through the scope and applies them together, before anything
+    /// re-renders. Every component they affect then renders once.
+    pub fn batch(&self, f: impl FnOnce(&BatchScope)) {
+        let scope = BatchScope {
+            resolvers: RefCell::default(),
+        };
+        f(&scope);
+        let resolvers = scope.resolvers.into_inner();
+        if !resolvers.is_empty() {
+            self.tx.send(EffectResolver::Batch(resolvers)).unwrap();
+        }
+    }
+
+    /// Holds the scroll position of an [`e::scroll`](crate::prelude::e::scroll) area rendered
+    /// by this component, re-rendering whenever it moves.
+    pub fn use_scroll(&self) -> ScrollController {
+        let (state, setter) = self.use_linked_state(ScrollState::default);
+        ScrollController {
+            owner: self.id,
+            offset: state.offset,
+            setter,
+        }
+    }
+
+    /// Measures `text` laid out on a single line in the UI font at `font_size`, or returns
+    /// `None` while the font is still loading. Measurements are cached across renders.
+    pub fn measure_text(&self, text: &str, font_size: f32) -> Option<Vec2> {
+        self.world
+            .get_resource::<TextMeasureCache>()
+            .unwrap()
+            .measure(
+                self.world.get_resource::<Assets<Font>>().unwrap(),
+                &self.world.get_resource::<FontHandle>().unwrap().0,
+                text,
+                font_size,
+            )
+    }
+
+    /// Shortens `text` with a trailing ellipsis until it fits in `max_width`, see
+    /// [`Fctx::measure_text`]. Returns `text` unchanged while the font is still loading.
+    pub fn truncate_text(&self, text: &str, font_size: f32, max_width: f32) -> String {
+        let fits = |text: &str| {
+            self.measure_text(text, font_size)
+                .map_or(true, |size| size.x <= max_width)
+        };
+        if fits(text) {
+            return text.to_string();
+        }
+        let chars = text.chars().collect::<Vec<_>>();
+        let with_ellipsis = |len: usize| chars[..len].iter().chain(&['…']).collect::<String>();
+        // Binary search for the longest prefix that still fits along with the ellipsis.
+        let (mut low, mut high) = (0, chars.len());
+        while low < high {
+            let mid = (low + high + 1) / 2;
+            if fits(&with_ellipsis(mid)) {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        with_ellipsis(low)
+    }
+
+    /// Builds an element only when `deps` change, handing out the cached one otherwise. As long
+    /// as it is cached, the reconciler skips the element's subtree entirely when this component
+    /// re-renders, instead of diffing an identical copy.
+    pub fn use_element<D: PartialEq + 'static>(
+        &self,
+        deps: D,
+        build: impl FnOnce() -> Element,
+    ) -> Element {
+        let element = self.use_plain(
+            || None::<(D, Arc<Element>)>,
+            |cached| {
+                if let Some((old, element)) = &*cached {
+                    if *old == deps {
+                        return element.clone();
+                    }
+                }
+                let element = Arc::new(build());
+                *cached = Some((deps, element.clone()));
+                element
+            },
+        );
+        shared_element(element)
+    }
+
+    /// Mutable storage that lives as long as the component and is never watched: changing it
+    /// doesn't re-render anything. For caches and scratch values read by effects.
+    pub fn use_ref<T: 'static>(&self, init: impl FnOnce() -> T) -> RefHandle<T> {
+        self.use_plain(
+            || RefHandle(Rc::new(RefCell::new(init()))),
+            |handle| handle.clone(),
+        )
+    }
+
+    /// Returns the `current` passed on the previous render, or `None` on the first one, e.g. to
+    /// animate from an old value to a new one.
+    pub fn use_previous<T: Clone + 'static>(&self, current: T) -> Option<T> {
+        self.use_plain(|| None, |previous| previous.replace(current))
+    }
+
+    /// Computes a value with `f` on the first render and whenever `deps` changed since, returning
+    /// the stored one otherwise.
+    pub fn use_memo<T: 'static, D: PartialEq + 'static>(
+        &self,
+        deps: D,
+        f: impl FnOnce() -> T,
+    ) -> Ref<'_, T> {
+        let value = self.use_plain(
+            || None::<(D, Arc<T>)>,
+            |memo| {
+                if let Some((old, value)) = &*memo {
+                    if *old == deps {
+                        return value.clone();
+                    }
+                }
+                let value = Arc::new(f());
+                *memo = Some((deps, value.clone()));
+                value
+            },
+        );
+        Ref::Rc(value)
+    }
+
+    /// Memoizes a value that owns something needing teardown. The value is rebuilt when `deps`
+    /// change, with the previous one handed to its `drop` closure once the render finishes.
+    /// The current value is torn down the same way when the component unmounts.
+    pub fn use_resource_memo<T: 'static, D: PartialEq + 'static>(
+        &self,
+        deps: D,
+        make: impl FnOnce() -> T,
+        drop: impl FnOnce(T, &mut World) + 'static,
+    ) -> Ref<'_, T> {
+        let value = self.use_slot(
+            || ResourceMemo::<T, D> { current: None },
+            |slot| {
+                if let Some((old_deps, value, _)) = &slot.current {
+                    if *old_deps == deps {
+                        return value.clone();
+                    }
+                }
+                if let Some((_, old, old_drop)) = slot.current.take() {
+                    self.nonsend_queue
+                        .borrow_mut()
+                        .push(Box::new(move |world| tear_down(old, old_drop, world)));
+                }
+                let value = Arc::new(make());
+                slot.current = Some((deps, value.clone(), Box::new(drop)));
+                value
+            },
+        );
+        Ref::Rc(value)
+    }
 }
 
-pub enum Ref<'a, T> {
-    Rc(Arc<T>),
-    Borrowed(&'a T),
+/// Backs [`Fctx::use_provide_context`].
+struct Provided<T>(T);
+
+/// The value provided to `id` by the closest [`Fctx::use_provide_context`] above it.
+fn provided<T: Send + Sync + 'static>(world: &World, id: MountedId) -> Option<&T> {
+    let provider = find_inherited::<Provided<T>>(world, id)?;
+    world
+        .get::<Provided<T>>(provider)
+        .map(|provided| &provided.0)
 }
 
-impl<'a, T: 'static> Deref for Ref<'a, T> {
-    type Target = T;
+/// Backs [`Fctx::use_world_query`], holding the query the component last rendered with and
+/// its result.
+struct WorldQuery<F, T> {
+    query: F,
+    last: T,
+}
 
-    fn deref(&self) -> &Self::Target {
-        match self {
-            Ref::Rc(v) => v,
-            Ref::Borrowed(v) => *v,
+/// Backs [`Fctx::use_focus_within`], holding the answer the component last rendered with.
+struct FocusWithin {
+    primitive: Entity,
+    inside: bool,
+}
+
+/// Whether the resource `T` changed since the last check, counting being inserted.
+fn resource_changed<T: Component>(world: &World) -> bool {
+    world.contains_resource::<T>() && world.is_resource_changed::<T>()
+}
+
+/// Every entity with a `T` component, sorted so the result only changes when the set does, for
+/// use with [`Fctx::use_condition`].
+pub fn entities_with<T: Component>(world: &World) -> Vec<Entity> {
+    let id = match world.components().get_id(TypeId::of::<T>()) {
+        Some(id) => id,
+        None => return Vec::new(),
+    };
+    let mut entities = world
+        .archetypes()
+        .iter()
+        .filter(|archetype| archetype.contains(id))
+        .flat_map(|archetype| archetype.entities().iter().copied())
+        .collect::<Vec<_>>();
+    entities.sort_unstable();
+    entities
+}
+
+/// The states of every call to a system-driven hook of one kind made by a component, in call
+/// order, see [`Fctx::use_hook_state`].
+pub(crate) struct HookStates<S>(Vec<S>);
+
+/// The index of a call's state in its [`HookStates`].
+struct StateIndex<S>(usize, PhantomData<fn() -> S>);
+
+/// Backs [`Fctx::use_interval_counter`]. The tick count is derived from a fixed origin rather than
+/// accumulated, so its component only re-renders on new ticks.
+pub(crate) struct IntervalCounter {
+    origin: f64,
+    period: f64,
+    ticks: u32,
+    /// Set on a new tick, until the check it triggers.
+    fresh: bool,
+}
+
+pub(crate) fn tick_interval_counters(
+    time: Res<Time>,
+    mut counters: Query<&mut HookStates<IntervalCounter>>,
+) {
+    let now = time.seconds_since_startup();
+    for mut counters in counters.iter_mut() {
+        for counter in &mut counters.0 {
+            let ticks = ((now - counter.origin) / counter.period) as u32;
+            if ticks != counter.ticks {
+                counter.ticks = ticks;
+                counter.fresh = true;
+            }
         }
     }
 }
 
-pub struct Setter<T: Component> {
-    tx: Tx,
-    e: Option<MountedId>,
-    _m: PhantomData<fn() -> T>,
+pub(crate) struct HookTimer {
+    timer: Timer,
+    /// Set when the timer elapses, until the render it triggers.
+    fired: bool,
 }
 
-impl<T: Component> Setter<T> {
-    pub fn set<F: FnOnce(Mut<T>) + 'static>(&self, f: F) {
-        if let Some(e) = self.e {
-            self.tx
-                .send(EffectResolver::MountedAccess(
-                    e,
-                    Box::new(move |w| f(w.entity_mut(e.0).get_mut().unwrap())),
-                ))
-                .unwrap();
-        } else {
-            self.tx
-                .send(EffectResolver::ResourceAccess(
-                    TypeId::of::<T>(),
-                    Box::new(|w| f(w.get_resource_mut().unwrap())),
-                ))
-                .unwrap();
+pub(crate) fn tick_hook_timers(time: Res<Time>, mut timers: Query<&mut HookStates<HookTimer>>) {
+    for mut timers in timers.iter_mut() {
+        for timer in &mut timers.0 {
+            if timer.timer.tick(time.delta()).just_finished() {
+                timer.fired = true;
+            }
         }
     }
 }
 
-impl<'a> Drop for Fctx<'a> {
-    fn drop(&mut self) {
-        for nonsend in self.nonsend_queue.get_mut().drain(..) {
-            nonsend(self.world);
+pub(crate) struct AsyncTask {
+    task: Option<Task<Box<dyn Any + Send + Sync>>>,
+    output: Option<Box<dyn Any + Send + Sync>>,
+    /// Set when the task completes, until the render it triggers.
+    fresh: bool,
+}
+
+pub(crate) fn poll_async_tasks(mut tasks: Query<&mut HookStates<AsyncTask>>) {
+    for mut tasks in tasks.iter_mut() {
+        for async_task in &mut tasks.0 {
+            let task = match &mut async_task.task {
+                Some(task) => task,
+                None => continue,
+            };
+            if let Some(output) = future::block_on(future::poll_once(task)) {
+                async_task.task = None;
+                async_task.output = Some(output);
+                async_task.fresh = true;
+            }
+        }
+    }
+}
+
+pub(crate) struct HoverDelay {
+    primitive: Entity,
+    delay: f64,
+    /// When the cursor entered the primitive, if it is over it.
+    since: Option<f64>,
+    shown: bool,
+    rendered: bool,
+}
+
+pub(crate) fn track_hover_delays(
+    time: Res<Time>,
+    windows: Res<Windows>,
+    nodes: Query<(&Node, &GlobalTransform)>,
+    mut delays: Query<&mut HookStates<HoverDelay>>,
+) {
+    let now = time.seconds_since_startup();
+    let cursor = windows.get_primary().and_then(|w| w.cursor_position());
+    for mut delays in delays.iter_mut() {
+        for hover in &mut delays.0 {
+            let hovered = match (cursor, nodes.get(hover.primitive)) {
+                (Some(cursor), Ok((node, transform))) => {
+                    let min = transform.translation.truncate() - node.size / 2.;
+                    let max = min + node.size;
+                    cursor.x >= min.x && cursor.x <= max.x && cursor.y >= min.y && cursor.y <= max.y
+                }
+                _ => false,
+            };
+            match (hovered, hover.since) {
+                (true, None) => hover.since = Some(now),
+                (false, Some(_)) => {
+                    hover.since = None;
+                    hover.shown = false;
+                }
+                (true, Some(since)) if !hover.shown && now - since >= hover.delay => {
+                    hover.shown = true
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The progress of a [`Fctx::use_async`] task.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AsyncState<T> {
+    Pending,
+    Ready(T),
+}
+
+/// How a [`Fctx::use_spring`] moves. Stiffer springs are faster, more damped ones overshoot less.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpringConfig {
+    pub stiffness: f32,
+    pub damping: f32,
+}
+
+impl Default for SpringConfig {
+    fn default() -> Self {
+        Self {
+            stiffness: 170.,
+            damping: 26.,
         }
     }
 }
+
+pub(crate) struct Spring {
+    value: f32,
+    velocity: f32,
+    target: f32,
+    config: SpringConfig,
+    /// Set when the spring steps, until the check it triggers.
+    moved: bool,
+}
+
+pub(crate) fn step_springs(time: Res<Time>, mut springs: Query<&mut HookStates<Spring>>) {
+    const MAX_STEP: f32 = 1. / 120.;
+    const EPSILON: f32 = 1e-3;
+    let delta = time.delta_seconds();
+    for mut springs in springs.iter_mut() {
+        for spring in &mut springs.0 {
+            // Settled springs are left untouched, so their components stop re-rendering.
+            if spring.value == spring.target && spring.velocity == 0. {
+                continue;
+            }
+            spring.moved = true;
+            let steps = (delta / MAX_STEP).ceil().max(1.);
+            let dt = delta / steps;
+            for _ in 0..steps as u32 {
+                let force = spring.config.stiffness * (spring.target - spring.value)
+                    - spring.config.damping * spring.velocity;
+                spring.velocity += force * dt;
+                spring.value += spring.velocity * dt;
+            }
+            if (spring.target - spring.value).abs() < EPSILON && spring.velocity.abs() < EPSILON {
+                spring.value = spring.target;
+                spring.velocity = 0.;
+            }
+        }
+    }
+}
+
+/// Restarts the count of a [`Fctx::use_interval_counter`] from zero.
+#[derive(Clone)]
+pub struct IntervalReset {
+    tx: Tx,
+    id: MountedId,
+    index: usize,
+}
+
+impl IntervalReset {
+    pub fn reset(&self) {
+        let (id, index) = (self.id, self.index);
+        self.tx
+            .send(EffectResolver::MountedAccess(
+                id,
+                Box::new(move |world| {
+                    let now = world
+                        .get_resource::<Time>()
+                        .unwrap()
+                        .seconds_since_startup();
+                    let mut counters = world.get_mut::<HookStates<IntervalCounter>>(id.0).unwrap();
+                    let counter = &mut counters.0[index];
+                    counter.origin = now;
+                    counter.ticks = 0;
+                }),
+            ))
+            .unwrap();
+    }
+}
+
+/// Controls the timer of a [`Fctx::use_timer`].
+#[derive(Clone)]
+pub struct TimerControls {
+    tx: Tx,
+    id: MountedId,
+    index: usize,
+}
+
+impl TimerControls {
+    /// Restarts the timer from zero, dropping an elapse not rendered yet.
+    pub fn reset(&self) {
+        self.with_timer(|timer| {
+            timer.timer.reset();
+            timer.fired = false;
+        });
+    }
+
+    /// Stops the timer where it is until [`TimerControls::unpause`].
+    pub fn pause(&self) {
+        self.with_timer(|timer| timer.timer.pause());
+    }
+
+    pub fn unpause(&self) {
+        self.with_timer(|timer| timer.timer.unpause());
+    }
+
+    fn with_timer(&self, f: impl FnOnce(&mut HookTimer) + Send + 'static) {
+        let (id, index) = (self.id, self.index);
+        self.tx
+            .send(EffectResolver::MountedAccess(
+                id,
+                Box::new(move |world| {
+                    f(&mut world.get_mut::<HookStates<HookTimer>>(id.0).unwrap().0[index])
+                }),
+            ))
+            .unwrap();
+    }
+}
+
+/// Subscription key for components rendering on every frame.
+struct EveryFrame;
+
+fn every_frame(_: &World) -> bool {
+    true
+}
+
+struct Plain<T>(T);
+
+impl<T: 'static> HookSlot for Plain<T> {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn teardown(self: Box<Self>, _: &mut World) {}
+}
+
+struct Stateful<S> {
+    state: Rc<RefCell<Option<S>>>,
+    on_unmount: fn(S, &mut World),
+}
+
+impl<S: 'static> HookSlot for Stateful<S> {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn teardown(self: Box<Self>, world: &mut World) {
+        let Stateful { state, on_unmount } = *self;
+        if let Some(state) = Rc::try_unwrap(state).ok().and_then(RefCell::into_inner) {
+            on_unmount(state, world);
+        }
+    }
+}
+
+struct ShortcutOwner(MountedId);
+
+impl HookSlot for ShortcutOwner {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn teardown(self: Box<Self>, world: &mut World) {
+        if let Some(mut registry) = world.get_resource_mut::<ShortcutRegistry>() {
+            registry.unregister(self.0);
+        }
+    }
+}
+
+/// The components below a suspense boundary that are still pending.
+#[derive(Default)]
+pub(crate) struct SuspenseBoundary {
+    pub(crate) pending: HashSet<MountedId>,
+}
+
+struct Suspender {
+    boundary: Option<Entity>,
+    id: MountedId,
+    pending: bool,
+}
+
+impl HookSlot for Suspender {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn teardown(self: Box<Self>, world: &mut World) {
+        if let (true, Some(boundary)) = (self.pending, self.boundary) {
+            if let Some(mut entity) = world.get_entity_mut(boundary) {
+                if let Some(mut suspense) = entity.get_mut::<SuspenseBoundary>() {
+                    suspense.pending.remove(&self.id);
+                }
+            }
+        }
+    }
+}
+
+/// The fields below a form that currently fail validation.
+#[derive(Default)]
+struct FormState {
+    invalid: HashSet<MountedId>,
+}
+
+struct Validation<T> {
+    form: Option<Entity>,
+    id: MountedId,
+    checked: Option<(T, Option<String>)>,
+    invalid: bool,
+}
+
+impl<T: 'static> HookSlot for Validation<T> {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn teardown(self: Box<Self>, world: &mut World) {
+        if let (true, Some(form)) = (self.invalid, self.form) {
+            if let Some(mut entity) = world.get_entity_mut(form) {
+                if let Some(mut state) = entity.get_mut::<FormState>() {
+                    state.invalid.remove(&self.id);
+                }
+            }
+        }
+    }
+}
+
+/// Undoes what a [`Fctx::use_effect`] did.
+pub type Cleanup = Box<dyn FnOnce(&mut World)>;
+
+struct Effect<D> {
+    deps: Option<D>,
+    cleanup: Rc<RefCell<Option<Cleanup>>>,
+}
+
+impl<D: 'static> HookSlot for Effect<D> {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn teardown(self: Box<Self>, world: &mut World) {
+        let cleanup = self.cleanup.borrow_mut().take();
+        if let Some(cleanup) = cleanup {
+            cleanup(world);
+        }
+    }
+}
+
+struct ResourceMemo<T, D> {
+    current: Option<(D, Arc<T>, Box<dyn FnOnce(T, &mut World)>)>,
+}
+
+impl<T: 'static, D: 'static> HookSlot for ResourceMemo<T, D> {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn teardown(self: Box<Self>, world: &mut World) {
+        if let Some((_, value, drop)) = self.current {
+            tear_down(value, drop, world);
+        }
+    }
+}
+
+/// Hands a [`Fctx::use_resource_memo`] value to its `drop` closure. A value still shared is
+/// reported instead, and dropped without it once the last clone is.
+fn tear_down<T: 'static>(value: Arc<T>, drop: Box<dyn FnOnce(T, &mut World)>, world: &mut World) {
+    match Arc::try_unwrap(value) {
+        Ok(value) => drop(value, world),
+        Err(_) => report(
+            world,
+            Diagnostic::ValueStillShared {
+                hook: "use_resource_memo",
+                value: std::any::type_name::<T>(),
+            },
+        ),
+    }
+}
+
+/// Shared handle to the value of a [`Fctx::use_ref`].
+pub struct RefHandle<T>(Rc<RefCell<T>>);
+
+impl<T> RefHandle<T> {
+    pub fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut self.0.borrow_mut())
+    }
+
+    pub fn set(&self, value: T) {
+        *self.0.borrow_mut() = value;
+    }
+
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.0.borrow().clone()
+    }
+}
+
+impl<T> Clone for RefHandle<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+pub enum Ref<'a, T> {
+    Rc(Arc<T>),
+    Borrowed(&'a T),
+}
+
+impl<'a, T: 'static> Deref for Ref<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Ref::Rc(v) => v,
+            Ref::Borrowed(v) => *v,
+        }
+    }
+}
+
+/// Collects updates for [`Fctx::batch`].
+pub struct BatchScope {
+    resolvers: RefCell<Vec<EffectResolver>>,
+}
+
+impl BatchScope {
+    /// Like [`Setter::set`], but applied with the rest of the batch.
+    pub fn set<T: Component, F: FnOnce(Mut<T>) + Send + 'static>(&self, setter: &Setter<T>, f: F) {
+        self.resolvers.borrow_mut().push(setter.resolver(f));
+    }
+}
+
+pub struct Setter<T: Component> {
+    tx: Tx,
+    e: Option<MountedId>,
+    _m: PhantomData<fn() -> T>,
+}
+
+impl<T: Component> Setter<T> {
+    /// Queues `f` to update the state. Everything queued by the next `process_messages` is
+    /// applied in order before anything renders, so setting several times in a frame
+    /// re-renders the component once, with the cumulative result.
+    pub fn set<F: FnOnce(Mut<T>) + Send + 'static>(&self, f: F) {
+        self.tx.send(self.resolver(f)).unwrap();
+    }
+
+    /// Queues overwriting the state with what `f` computes from its value at that point, e.g.
+    /// `set_count.replace(|count| Count(count.0 + 1))`.
+    pub fn replace<F: FnOnce(&T) -> T + Send + 'static>(&self, f: F) {
+        self.set(move |mut value| *value = f(&value));
+    }
+
+    /// Queues overwriting the state with `value`.
+    pub fn set_value(&self, value: T) {
+        self.set(move |mut old| *old = value);
+    }
+
+    fn resolver<F: FnOnce(Mut<T>) + Send + 'static>(&self, f: F) -> EffectResolver {
+        if let Some(e) = self.e {
+            EffectResolver::MountedAccess(
+                e,
+                // Linked state that couldn't be inserted isn't there yet; the set is dropped and
+                // the state is made again on the next render.
+                Box::new(move |w| {
+                    if let Some(value) = w.entity_mut(e.0).get_mut() {
+                        f(value)
+                    }
+                }),
+            )
+        } else {
+            EffectResolver::ResourceAccess(
+                TypeId::of::<T>(),
+                Box::new(|w| f(w.get_resource_mut().unwrap())),
+            )
+        }
+    }
+
+    /// Narrows this setter down to a part of `T`, for handing to children that only edit one field.
+    /// Changes made through the lens still mark the whole `T` as changed.
+    pub fn lens<U: 'static>(&self, get_mut: fn(&mut T) -> &mut U) -> Lens<T, U> {
+        Lens {
+            setter: self.clone(),
+            get_mut: Arc::new(get_mut),
+        }
+    }
+}
+
+impl<T: Component> Clone for Setter<T> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            e: self.e,
+            _m: PhantomData,
+        }
+    }
+}
+
+/// Updates the state of a [`Fctx::use_state`] hook, re-rendering its component.
+pub struct StateSetter<T> {
+    tx: Tx,
+    id: MountedId,
+    state: Arc<Mutex<Arc<T>>>,
+}
+
+impl<T: Clone + Send + Sync + 'static> StateSetter<T> {
+    pub fn set<F: FnOnce(&mut T) + Send + 'static>(&self, f: F) {
+        let state = self.state.clone();
+        self.tx
+            .send(EffectResolver::MountedAccess(
+                self.id,
+                Box::new(move |_| f(Arc::make_mut(&mut state.lock().unwrap()))),
+            ))
+            .unwrap();
+    }
+}
+
+impl<T> Clone for StateSetter<T> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            id: self.id,
+            state: self.state.clone(),
+        }
+    }
+}
+
+pub struct Lens<T: Component, U: 'static> {
+    setter: Setter<T>,
+    get_mut: Projection<T, U>,
+}
+
+type Projection<T, U> = Arc<dyn for<'x> Fn(&'x mut T) -> &'x mut U + Send + Sync>;
+
+// Pins the closure to a higher-ranked signature, which inference can't do on its own.
+fn projection<T, U, F>(f: F) -> Projection<T, U>
+where
+    F: for<'x> Fn(&'x mut T) -> &'x mut U + Send + Sync + 'static,
+{
+    Arc::new(f)
+}
+
+impl<T: Component, U: 'static> Lens<T, U> {
+    pub fn set<F: FnOnce(&mut U) + Send + 'static>(&self, f: F) {
+        let get_mut = self.get_mut.clone();
+        self.setter.set(move |mut v| f(get_mut(&mut *v)));
+    }
+
+    pub fn lens<V: 'static>(&self, get_mut: fn(&mut U) -> &mut V) -> Lens<T, V> {
+        let outer = self.get_mut.clone();
+        Lens {
+            setter: self.setter.clone(),
+            get_mut: projection(move |v| get_mut(outer(v))),
+        }
+    }
+}
+
+impl<T: Component, U: 'static> Clone for Lens<T, U> {
+    fn clone(&self) -> Self {
+        Self {
+            setter: self.setter.clone(),
+            get_mut: self.get_mut.clone(),
+        }
+    }
+}
+
+impl<'a> Drop for Fctx<'a> {
+    fn drop(&mut self) {
+        // A render that panicked never finished, so neither do its effects.
+        if self.discard || std::thread::panicking() {
+            return;
+        }
+        for nonsend in self.nonsend_queue.get_mut().drain(..) {
+            nonsend(self.world);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        any::Any,
+        cell::RefCell,
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    use bevy::{
+        prelude::{Entity, IntoSystem, Stage, SystemStage, With, World},
+        ui::Interaction,
+    };
+
+    use super::{poll_async_tasks, HookStates, HookTimer, HoverDelay, IntervalCounter, Spring};
+    use crate::prelude::*;
+
+    struct Count(u32);
+
+    #[test]
+    fn setter_moves_into_click_handlers() {
+        fn counter(ctx: Fctx) -> Element {
+            let (count, set_count) = ctx.use_linked_state(|| Count(0));
+            e::button([e::text(count.0.to_string())])
+                .on_click(move |_| set_count.set(|mut count| count.0 += 1))
+        }
+
+        let mut harness = TestHarness::new();
+        harness.mount(counter.e(()));
+        harness.click("0");
+        assert_eq!(harness.snapshot(), "Button\n  Text \"1\"\n");
+    }
+
+    #[test]
+    fn state_setter_moves_into_click_handlers() {
+        fn counter(ctx: Fctx) -> Element {
+            let (count, set_count) = ctx.use_state(|| 0);
+            e::button([e::text(count.to_string())]).on_click(move |_| set_count.set(|c| *c += 1))
+        }
+
+        let mut harness = TestHarness::new();
+        harness.mount(counter.e(()));
+        harness.click("0");
+        harness.click("1");
+        assert_eq!(harness.snapshot(), "Button\n  Text \"2\"\n");
+    }
+
+    struct Form {
+        name: String,
+    }
+
+    #[test]
+    fn lens_passes_to_children_as_a_prop() {
+        fn rename(_: Fctx, name: &Lens<Form, String>) -> Element {
+            let name = name.clone();
+            e::button([e::text("Rename")]).on_click(move |_| name.set(|name| *name = "Ann".into()))
+        }
+
+        fn form(ctx: Fctx) -> Element {
+            let (form, set_form) = ctx.use_linked_state(|| Form { name: "Bob".into() });
+            e::node([
+                e::text(form.name.clone()),
+                rename.e(set_form.lens(|form| &mut form.name)),
+            ])
+        }
+
+        let mut harness = TestHarness::new();
+        harness.mount(form.e(()));
+        harness.click("Rename");
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Text \"Ann\"\n  Button\n    Text \"Rename\"\n",
+        );
+    }
+
+    #[test]
+    fn interval_counters_count_separately() {
+        fn counters(ctx: Fctx) -> Element {
+            let (fast, _) = ctx.use_interval_counter(Duration::from_millis(100));
+            let (slow, _) = ctx.use_interval_counter(Duration::from_secs(1));
+            e::text(format!("{} {}", fast, slow))
+        }
+
+        let mut harness = TestHarness::new();
+        let root = harness.mount(counters.e(()));
+        harness.tick();
+        let mut states = harness
+            .world
+            .get_mut::<HookStates<IntervalCounter>>(root.id().entity())
+            .unwrap();
+        states.0[1].ticks = 2;
+        states.0[1].fresh = true;
+        harness.tick();
+        assert_eq!(harness.snapshot(), "Text \"0 2\"\n");
+    }
+
+    #[test]
+    fn springs_move_separately() {
+        fn springs(ctx: Fctx) -> Element {
+            let x = ctx.use_spring(1., SpringConfig::default());
+            let y = ctx.use_spring(2., SpringConfig::default());
+            e::text(format!("{} {}", x, y))
+        }
+
+        let mut harness = TestHarness::new();
+        let root = harness.mount(springs.e(()));
+        assert_eq!(harness.snapshot(), "Text \"1 2\"\n");
+        harness.tick();
+        let mut states = harness
+            .world
+            .get_mut::<HookStates<Spring>>(root.id().entity())
+            .unwrap();
+        states.0[0].value = 0.5;
+        states.0[0].moved = true;
+        harness.tick();
+        assert_eq!(harness.snapshot(), "Text \"0.5 2\"\n");
+    }
+
+    struct Score(u32);
+
+    #[test]
+    fn conditions_watch_separately() {
+        fn scores(ctx: Fctx) -> Element {
+            let score = ctx.use_condition(|world| world.get_resource::<Score>().unwrap().0);
+            let doubled = ctx.use_condition(|world| world.get_resource::<Score>().unwrap().0 * 2);
+            e::text(format!("{} {}", score, doubled))
+        }
+
+        let mut harness = TestHarness::new();
+        harness.world.insert_resource(Score(1));
+        harness.mount(scores.e(()));
+        harness.world.get_resource_mut::<Score>().unwrap().0 = 2;
+        harness.tick();
+        assert_eq!(harness.snapshot(), "Text \"2 4\"\n");
+    }
+
+    struct Enemy;
+
+    #[test]
+    fn checks_firing_together_render_once() {
+        fn enemies(ctx: Fctx) -> Element {
+            let enemies = ctx.use_condition(entities_with::<Enemy>);
+            let score = ctx.use_condition(|world| world.get_resource::<Score>().unwrap().0);
+            e::text(format!("{} {}", enemies.len(), score))
+        }
+
+        let mut harness = TestHarness::new();
+        harness.world.insert_resource(Score(0));
+        harness.mount(enemies.e(()));
+        harness.world.spawn().insert(Enemy);
+        harness.world.get_resource_mut::<Score>().unwrap().0 = 1;
+        harness.tick();
+        assert_eq!(harness.snapshot(), "Text \"1 1\"\n");
+        assert_eq!(harness.context().stats().last_render_count, 1);
+        harness.tick();
+        assert_eq!(harness.context().stats().last_render_count, 0);
+    }
+
+    #[test]
+    fn entities_moving_archetypes_dont_rerender() {
+        struct Boss;
+
+        fn enemies(ctx: Fctx) -> Element {
+            let enemies = ctx.use_condition(entities_with::<Enemy>);
+            e::text(format!("{}", enemies.len()))
+        }
+
+        let mut harness = TestHarness::new();
+        let first = harness.world.spawn().insert(Enemy).id();
+        harness.world.spawn().insert(Enemy);
+        harness.mount(enemies.e(()));
+        // Now after the second enemy in archetype order, but the set is the same.
+        harness.world.entity_mut(first).insert(Boss);
+        harness.tick();
+        assert_eq!(harness.context().stats().last_render_count, 0);
+    }
+
+    #[test]
+    fn focus_within_watches_separately() {
+        fn panes(ctx: Fctx, left: &PrimitiveId, right: &PrimitiveId) -> Element {
+            let left = ctx.use_focus_within(*left);
+            let right = ctx.use_focus_within(*right);
+            e::text(format!("{} {}", left, right))
+        }
+
+        let mut harness = TestHarness::new();
+        let left = PrimitiveId(harness.world.spawn().id());
+        let right = PrimitiveId(harness.world.spawn().id());
+        harness.mount(panes.e((left, right)));
+        harness.world.insert_resource(Focused(Some(right.entity())));
+        harness.tick();
+        assert_eq!(harness.snapshot(), "Text \"false true\"\n");
+    }
+
+    #[test]
+    fn hover_delays_show_separately() {
+        fn tooltips(ctx: Fctx, primitive: &PrimitiveId) -> Element {
+            let short = ctx.use_hover_delay(*primitive, Duration::from_millis(100));
+            let long = ctx.use_hover_delay(*primitive, Duration::from_secs(1));
+            e::text(format!("{} {}", short, long))
+        }
+
+        let mut harness = TestHarness::new();
+        let primitive = PrimitiveId(harness.world.spawn().id());
+        let root = harness.mount(tooltips.e(primitive));
+        harness.tick();
+        let mut states = harness
+            .world
+            .get_mut::<HookStates<HoverDelay>>(root.id().entity())
+            .unwrap();
+        states.0[0].shown = true;
+        harness.tick();
+        assert_eq!(harness.snapshot(), "Text \"true false\"\n");
+    }
+
+    struct Health(u32);
+
+    #[test]
+    fn world_query_reads_another_entity() {
+        fn health_bar(ctx: Fctx, target: &Entity) -> Element {
+            let target = *target;
+            let health = ctx.use_world_query(move |world| world.get::<Health>(target).unwrap().0);
+            e::text(health.to_string())
+        }
+
+        let mut harness = TestHarness::new();
+        let player = harness.world.spawn().insert(Health(3)).id();
+        harness.mount(health_bar.e(player));
+        harness.tick();
+        harness.world.get_mut::<Health>(player).unwrap().0 = 2;
+        harness.tick();
+        assert_eq!(harness.snapshot(), "Text \"2\"\n");
+        assert_eq!(harness.context().stats().last_render_count, 1);
+    }
+
+    #[test]
+    fn hover_follows_the_rendered_primitive() {
+        fn hoverable(ctx: Fctx) -> Element {
+            let label = if ctx.use_hover() { "Hovered" } else { "Idle" };
+            e::button([e::text(label)])
+        }
+
+        let mut harness = TestHarness::new();
+        harness.mount(hoverable.e(()));
+        let button = harness
+            .world
+            .query_filtered::<Entity, With<Interaction>>()
+            .iter(&harness.world)
+            .next()
+            .unwrap();
+        *harness.world.get_mut::<Interaction>(button).unwrap() = Interaction::Hovered;
+        harness.tick();
+        assert_eq!(harness.snapshot(), "Button\n  Text \"Hovered\"\n");
+        *harness.world.get_mut::<Interaction>(button).unwrap() = Interaction::None;
+        harness.tick();
+        assert_eq!(harness.snapshot(), "Button\n  Text \"Idle\"\n");
+    }
+
+    #[test]
+    fn timers_fire_separately() {
+        fn timers(ctx: Fctx) -> Element {
+            let (fast, _) = ctx.use_timer(0.1);
+            let (slow, _) = ctx.use_timer(1.);
+            e::text(format!("{} {}", fast, slow))
+        }
+
+        let mut harness = TestHarness::new();
+        let root = harness.mount(timers.e(()));
+        harness.tick();
+        let mut states = harness
+            .world
+            .get_mut::<HookStates<HookTimer>>(root.id().entity())
+            .unwrap();
+        states.0[1].fired = true;
+        harness.tick();
+        assert_eq!(harness.snapshot(), "Text \"false true\"\n");
+        // Firing is only seen by the render it triggers.
+        harness.tick();
+        assert_eq!(harness.context().stats().last_render_count, 0);
+    }
+
+    #[test]
+    fn async_tasks_complete_separately() {
+        fn loader(ctx: Fctx) -> Element {
+            let first = ctx.use_async(async { 1 });
+            let second = ctx.use_async(async { 2 });
+            e::text(format!("{:?} {:?}", first, second))
+        }
+
+        let mut harness = TestHarness::new();
+        harness.mount(loader.e(()));
+        let mut poll = SystemStage::single(poll_async_tasks.system());
+        for _ in 0..100 {
+            poll.run(&mut harness.world);
+            harness.tick();
+            if harness.snapshot() == "Text \"Ready(1) Ready(2)\"\n" {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("tasks never completed, rendered {:?}", harness.snapshot());
+    }
+
+    #[test]
+    fn context_consumers_render_on_changes_only() {
+        fn level(ctx: Fctx) -> Element {
+            e::text(format!("Level {}", *ctx.use_context::<u32>().unwrap()))
+        }
+
+        fn game(ctx: Fctx) -> Element {
+            let (clicks, set_clicks) = ctx.use_state(|| 0u32);
+            ctx.use_provide_context(*clicks / 2);
+            e::node([
+                e::button([e::text(format!("Clicked {}", *clicks))])
+                    .on_click(move |_| set_clicks.set(|clicks| *clicks += 1)),
+                level.memo_e(()),
+            ])
+        }
+
+        let mut harness = TestHarness::new();
+        harness.mount(game.e(()));
+        harness.click("Clicked 0");
+        harness.tick();
+        assert_eq!(harness.context().stats().last_render_count, 0);
+        harness.click("Clicked 1");
+        harness.tick();
+        assert_eq!(harness.context().stats().last_render_count, 1);
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Button\n    Text \"Clicked 2\"\n  Text \"Level 1\"\n",
+        );
+    }
+
+    #[test]
+    fn values_kept_past_the_render_are_reported() {
+        thread_local! {
+            static KEPT: RefCell<Vec<Arc<dyn Any>>> = RefCell::new(Vec::new());
+        }
+
+        fn keep_first<T: 'static>(value: &Ref<T>) {
+            KEPT.with(|kept| {
+                let mut kept = kept.borrow_mut();
+                match value {
+                    Ref::Rc(value) if kept.len() < 2 => kept.push(value.clone()),
+                    _ => {}
+                }
+            });
+        }
+
+        fn keeping(ctx: Fctx) -> Element {
+            let (count, set_count) = ctx.use_linked_state(|| Count(1));
+            let tenfold = ctx.use_resource_memo(count.0, || count.0 * 10, |_, _| {});
+            keep_first(&count);
+            keep_first(&tenfold);
+            e::button([e::text(format!("{} {}", count.0, *tenfold))])
+                .on_click(move |_| set_count.set(|mut count| count.0 += 1))
+        }
+
+        let mut harness = TestHarness::new();
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let sink = reported.clone();
+        harness
+            .world
+            .insert_resource(HookedDiagnostics::new(move |diagnostic| {
+                sink.lock().unwrap().push(diagnostic)
+            }));
+        harness.mount(keeping.e(()));
+        // The state couldn't be inserted, so the set is dropped and the render it causes makes
+        // the state again.
+        harness.click("1 10");
+        assert_eq!(harness.snapshot(), "Button\n  Text \"1 10\"\n");
+        harness.click("1 10");
+        assert_eq!(harness.snapshot(), "Button\n  Text \"2 20\"\n");
+        assert_eq!(
+            *reported.lock().unwrap(),
+            [
+                Diagnostic::ValueStillShared {
+                    hook: "use_linked_state",
+                    value: std::any::type_name::<Count>(),
+                },
+                Diagnostic::ValueStillShared {
+                    hook: "use_resource_memo",
+                    value: "u32",
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn state_is_hidden_from_queries() {
+        #[derive(Clone)]
+        struct Secret(u32);
+
+        fn keeper(ctx: Fctx) -> Element {
+            let (secret, set_secret) = ctx.use_state(|| Secret(1));
+            e::button([e::text(secret.0.to_string())])
+                .on_click(move |_| set_secret.set(|secret| secret.0 += 1))
+        }
+
+        let mut harness = TestHarness::new();
+        harness.mount(keeper.e(()));
+        harness.click("1");
+        assert_eq!(harness.snapshot(), "Button\n  Text \"2\"\n");
+        let mut secrets = harness.world.query::<&Secret>();
+        assert_eq!(secrets.iter(&harness.world).count(), 0);
+    }
+
+    #[test]
+    fn use_previous_lags_one_render_behind() {
+        fn counter(ctx: Fctx) -> Element {
+            let (count, set_count) = ctx.use_state(|| 1);
+            let previous = ctx.use_previous(*count);
+            e::button([e::text(format!("{} after {:?}", count, previous))])
+                .on_click(move |_| set_count.set(|c| *c += 1))
+        }
+
+        let mut harness = TestHarness::new();
+        harness.mount(counter.e(()));
+        assert_eq!(harness.snapshot(), "Button\n  Text \"1 after None\"\n");
+        harness.click("1 after None");
+        assert_eq!(harness.snapshot(), "Button\n  Text \"2 after Some(1)\"\n");
+        harness.click("2 after Some(1)");
+        assert_eq!(harness.snapshot(), "Button\n  Text \"3 after Some(2)\"\n");
+    }
+
+    struct Total(u32);
+
+    #[test]
+    fn replace_and_set_value_work_on_resources_and_mounted_state() {
+        fn counters(ctx: Fctx) -> Element {
+            let total = ctx.use_resource::<Total>().0;
+            let set_total = ctx.use_resource_setter::<Total>();
+            let (count, set_count) = ctx.use_linked_state(|| Count(0));
+            let (reset_total, reset_count) = (set_total.clone(), set_count.clone());
+            e::node([
+                e::text(format!("{} {}", total, count.0)),
+                e::button([e::text("Add")]).on_click(move |_| {
+                    set_total.replace(|total| Total(total.0 + 2));
+                    set_count.replace(|count| Count(count.0 + 1));
+                }),
+                e::button([e::text("Reset")]).on_click(move |_| {
+                    reset_total.set_value(Total(10));
+                    reset_count.set_value(Count(5));
+                }),
+            ])
+        }
+
+        let mut harness = TestHarness::new();
+        harness.world.insert_resource(Total(0));
+        harness.mount(counters.e(()));
+        harness.click("Add");
+        harness.click("Add");
+        assert!(harness.snapshot().contains("Text \"4 2\""));
+        harness.click("Reset");
+        assert!(harness.snapshot().contains("Text \"10 5\""));
+    }
+
+    struct CleanedUp(Vec<u64>);
+
+    fn subscription(ctx: Fctx, id: &u64) -> Element {
+        let id = *id;
+        ctx.use_effect(id, move || -> Cleanup {
+            Box::new(move |world: &mut World| {
+                world.get_resource_mut::<CleanedUp>().unwrap().0.push(id)
+            })
+        });
+        e::text(id.to_string())
+    }
+
+    fn subscriptions(ctx: Fctx) -> Element {
+        let (ids, set_ids) = ctx.use_state(|| vec![1u64, 2, 3]);
+        e::node([
+            e::button([e::text("Remove")]).on_click(move |_| {
+                set_ids.set(|ids| {
+                    ids.remove(1);
+                })
+            }),
+            e::node(
+                ids.iter()
+                    .map(|id| subscription.e(*id).with_key(Key(*id)))
+                    .collect::<Vec<_>>(),
+            ),
+        ])
+    }
+
+    #[test]
+    fn effects_clean_up_once_when_their_keyed_component_is_removed() {
+        let mut harness = TestHarness::new();
+        harness.world.insert_resource(CleanedUp(Vec::new()));
+        harness.mount(subscriptions.e(()));
+        harness.click("Remove");
+        harness.tick();
+        harness.tick();
+        assert_eq!(harness.world.get_resource::<CleanedUp>().unwrap().0, [2]);
+        harness.click("Remove");
+        assert_eq!(harness.world.get_resource::<CleanedUp>().unwrap().0, [2, 3]);
+    }
+
+    #[test]
+    fn use_memo_recomputes_only_when_deps_change() {
+        thread_local! {
+            static COMPUTED: RefCell<u32> = RefCell::new(0);
+        }
+
+        fn square(ctx: Fctx) -> Element {
+            let (n, set_n) = ctx.use_state(|| 2);
+            let (_, set_renders) = ctx.use_state(|| 0);
+            let squared = ctx.use_memo(*n, || {
+                COMPUTED.with(|computed| *computed.borrow_mut() += 1);
+                *n * *n
+            });
+            e::node([
+                e::button([e::text("Grow")]).on_click(move |_| set_n.set(|n| *n += 1)),
+                e::button([e::text("Refresh")]).on_click(move |_| set_renders.set(|r| *r += 1)),
+                e::text(squared.to_string()),
+            ])
+        }
+
+        let mut harness = TestHarness::new();
+        harness.mount(square.e(()));
+        harness.click("Refresh");
+        harness.click("Refresh");
+        assert_eq!(COMPUTED.with(|computed| *computed.borrow()), 1);
+        harness.click("Grow");
+        harness.click("Refresh");
+        assert_eq!(COMPUTED.with(|computed| *computed.borrow()), 2);
+        assert!(harness.snapshot().contains("Text \"9\""));
+    }
+
+    #[test]
+    fn try_use_resource_renders_once_the_resource_is_inserted() {
+        fn total(ctx: Fctx) -> Element {
+            match ctx.try_use_resource::<Total>() {
+                Some(total) => e::text(total.0.to_string()),
+                None => e::text("Loading"),
+            }
+        }
+
+        let mut harness = TestHarness::new();
+        harness.mount(total.e(()));
+        assert_eq!(harness.snapshot(), "Text \"Loading\"\n");
+        harness.tick();
+        harness.world.insert_resource(Total(7));
+        harness.tick();
+        assert_eq!(harness.snapshot(), "Text \"7\"\n");
+    }
+
+    /// What ran during unmounting, in order.
+    struct Unmounted(Vec<String>);
+
+    fn record(world: &mut World, what: String) {
+        world.get_resource_mut::<Unmounted>().unwrap().0.push(what);
+    }
+
+    fn nested_effect(ctx: Fctx, name: &&'static str, depth: &u32) -> Element {
+        let (name, depth) = (*name, *depth);
+        ctx.use_linked_state(|| Count(depth));
+        let entity = ctx.id.entity();
+        ctx.use_effect((), move || -> Cleanup {
+            Box::new(move |world: &mut World| {
+                // Linked state is still around while cleaning up.
+                let count = world.get::<Count>(entity).map(|count| count.0);
+                record(world, format!("{} {:?}", name, count));
+            })
+        });
+        match depth {
+            0 => e::text(name),
+            depth => e::node([
+                nested_effect.e(("left", depth - 1)),
+                nested_effect.e(("right", depth - 1)),
+            ]),
+        }
+    }
+
+    #[test]
+    fn children_clean_up_before_their_parent() {
+        let mut harness = TestHarness::new();
+        harness.world.insert_resource(Unmounted(Vec::new()));
+        let root = harness.mount(e::node([nested_effect.e(("top", 1))]));
+        harness.replace_root(root, e::node(Vec::new()));
+        assert_eq!(
+            harness.world.get_resource::<Unmounted>().unwrap().0,
+            ["left Some(0)", "right Some(0)", "top Some(1)"],
+        );
+    }
+
+    #[test]
+    fn sets_in_one_handler_render_once() {
+        fn counter(ctx: Fctx) -> Element {
+            let (count, set_count) = ctx.use_linked_state(|| Count(0));
+            e::button([e::text(count.0.to_string())]).on_click(move |_| {
+                set_count.set(|mut count| count.0 += 1);
+                set_count.set(|mut count| count.0 *= 10);
+                set_count.set(|mut count| count.0 += 2);
+            })
+        }
+
+        let mut harness = TestHarness::new();
+        harness.mount(counter.e(()));
+        harness.click("0");
+        assert_eq!(harness.snapshot(), "Button\n  Text \"12\"\n");
+        assert_eq!(harness.context().stats().last_render_count, 1);
+    }
+}
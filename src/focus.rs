@@ -0,0 +1,37 @@
+use bevy::prelude::*;
+
+/// The focused entity, if any. Clicking a button or text input focuses it and clicking anywhere
+/// else clears focus. Apps with other rules, e.g. keyboard navigation, can set it themselves.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Focused(pub Option<Entity>);
+
+pub(crate) fn focus_on_click(
+    mouse: Res<Input<MouseButton>>,
+    interactions: Query<(Entity, &Interaction), Changed<Interaction>>,
+    mut focused: ResMut<Focused>,
+) {
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let clicked = interactions
+        .iter()
+        .find(|(_, interaction)| **interaction == Interaction::Clicked)
+        .map(|(entity, _)| entity);
+    if focused.0 != clicked {
+        focused.0 = clicked;
+    }
+}
+
+/// Whether the focused entity is `ancestor` or somewhere below it.
+pub(crate) fn focus_within(world: &World, ancestor: Entity) -> bool {
+    let mut current = world
+        .get_resource::<Focused>()
+        .and_then(|focused| focused.0);
+    while let Some(entity) = current {
+        if entity == ancestor {
+            return true;
+        }
+        current = world.get::<Parent>(entity).map(|parent| parent.0);
+    }
+    false
+}
@@ -1,38 +1,73 @@
 use bevy::{
-    prelude::{Entity, World},
+    ecs::{component::Component, world::EntityMut},
+    math::{Rect, Size},
+    prelude::{Color, Entity, Handle, Texture, World},
+    transform::hierarchy::despawn_with_children_recursive,
+    ui::{
+        AlignItems, AlignSelf, Display, FlexDirection, JustifyContent, Overflow, PositionType,
+        Style, Val,
+    },
     utils::{HashMap, HashSet},
 };
 use std::{
     any::{Any, TypeId},
-    hash::Hash,
+    collections::{hash_map::DefaultHasher, VecDeque},
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem,
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
 use crossbeam_channel::{Receiver, Sender};
 
-use crate::dom::{Dom, PrimitiveData, PrimitiveId};
+use crate::dom::{
+    ClickHandler, Dom, Primitive, PrimitiveData, PrimitiveId, RawSpawn, Role, StyleSheet,
+    TextOverrides, Theme, UiScale, UiTransform,
+};
+
+#[cfg(feature = "panic_recovery")]
+use crate::diagnostics::panic_message;
+use crate::diagnostics::{report, Diagnostic};
 
-use crate::fctx::Fctx;
+use crate::fctx::{Fctx, HookSlot, SuspenseBoundary};
+
+use crate::scroll::{ScrollController, ScrollViewport};
 
 pub(crate) type Tx = Sender<EffectResolver>;
 pub(crate) type Rx = Receiver<EffectResolver>;
 
 pub(crate) enum EffectResolver {
     Flag(MountedId),
-    ResourceAccess(TypeId, Box<dyn FnOnce(&mut World)>),
-    MountedAccess(MountedId, Box<dyn FnOnce(&mut World)>),
+    ResourceAccess(TypeId, Box<dyn FnOnce(&mut World) + Send>),
+    MountedAccess(MountedId, Box<dyn FnOnce(&mut World) + Send>),
+    /// Applied as a single step, see [`Fctx::batch`].
+    Batch(Vec<EffectResolver>),
 }
 
 impl EffectResolver {
-    fn resolve(self, world: &mut World) -> ResolveResult {
+    fn resolve(self, world: &mut World, results: &mut Vec<ResolveResult>) {
         match self {
-            EffectResolver::Flag(id) => ResolveResult::Mounted(id),
+            // It unmounted since, e.g. with a timer firing in the frame it did.
+            EffectResolver::Flag(id) if world.get::<Mounted>(id.0).is_none() => {}
+            EffectResolver::Flag(id) => results.push(ResolveResult::Mounted(id)),
             EffectResolver::ResourceAccess(id, f) => {
                 f(world);
-                ResolveResult::Resource(id)
+                results.push(ResolveResult::Resource(id));
+            }
+            EffectResolver::MountedAccess(id, _) if world.get::<Mounted>(id.0).is_none() => {
+                report(world, Diagnostic::UnmountedUpdate { entity: id.0 });
             }
             EffectResolver::MountedAccess(id, f) => {
                 f(world);
-                ResolveResult::Mounted(id)
+                results.push(ResolveResult::Mounted(id));
+            }
+            EffectResolver::Batch(resolvers) => {
+                for resolver in resolvers {
+                    resolver.resolve(world, results);
+                }
             }
         }
     }
@@ -43,27 +78,90 @@ enum ResolveResult {
     Resource(TypeId),
 }
 
-#[derive(Clone, Copy, Hash, PartialEq, Eq)]
-pub(crate) struct MountedId(pub Entity);
+/// Identifies a mounted element. It stays the same for as long as the element stays mounted,
+/// so it can be used to key user-side maps.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct MountedId(pub(crate) Entity);
+
+impl MountedId {
+    /// The entity holding the element's bookkeeping and, for components, their linked state.
+    pub fn entity(&self) -> Entity {
+        self.0
+    }
+}
 
-#[derive(Clone, Copy, Hash, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
 pub struct MountedRootId(MountedId);
-#[derive(Clone, Copy, Hash, PartialEq, Eq)]
+
+impl MountedRootId {
+    pub fn id(&self) -> MountedId {
+        self.0
+    }
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
 pub struct Key(pub u64);
 
+/// Implemented by functions taking an [`Fctx`] and references to their props.
+///
+/// `move` closures work too. A component's identity is its function type, and every closure
+/// expression has a type of its own, so the same closure expression rendered again updates the
+/// mounted component with its new captures. Memoized closures only compare their props, so
+/// anything captured that should trigger a re-render must be passed as a prop instead.
+///
+/// Props are passed as a tuple, e.g. `counter.e(())` or `labeled.e((name, value))`. A single
+/// prop can be passed without one, `blinker.e(3.)` being the same as `blinker.e((3.,))`.
 pub trait ComponentFunc<P, M>: Send + Sync + 'static {
     fn e(&self, p: P) -> Element;
+    /// Like [`ComponentFunc::e`], but the component skips re-rendering along with its parent
+    /// while its props compare equal to the previous ones. Props are compared as a whole with
+    /// `PartialEq`, so any props implementing it work, including `Vec`s, `String`s and nested
+    /// tuples.
     fn memo_e(&self, p: P) -> Element
     where
         P: PartialEq;
     fn call(&self, p: &P, ctx: Fctx) -> ComponentOutput;
     fn fn_type_id(&self) -> TypeId;
+    fn fn_type_name(&self) -> &'static str;
     fn dyn_clone(&self) -> Box<dyn ComponentFunc<P, M>>;
 }
 
+/// A component written as a lifecycle object instead of a function using hooks. The value
+/// implementing it serves as the component's props.
+///
+/// `init` runs on the first render only, so it must not call hooks keeping per-render storage
+/// like [`Fctx::use_ref`]; call those from `render`, which runs on every render.
+pub trait StatefulComponent: Clone + Send + Sync + 'static {
+    type State: 'static;
+
+    fn init(&self, ctx: &Fctx) -> Self::State;
+
+    fn render(&self, state: &mut Self::State, ctx: &Fctx) -> Element;
+
+    fn on_unmount(_state: Self::State, _world: &mut World) {}
+
+    fn e(self) -> Element
+    where
+        Self: Sized,
+    {
+        stateful_component::<Self>.e((self,))
+    }
+}
+
+fn stateful_component<C: StatefulComponent>(ctx: Fctx, component: &C) -> Element {
+    let state = ctx.use_stateful(C::on_unmount);
+    if state.borrow().is_none() {
+        let initial = component.init(&ctx);
+        *state.borrow_mut() = Some(initial);
+    }
+    let mut state = state.borrow_mut();
+    component.render(state.as_mut().unwrap(), &ctx)
+}
+
 trait DynComponentFunc: Send + Sync {
     fn call(&self, p: &dyn Prop, ctx: Fctx) -> ComponentOutput;
     fn fn_type_id(&self) -> TypeId;
+    fn fn_type_name(&self) -> &'static str;
     fn dyn_clone(&self) -> Box<dyn DynComponentFunc>;
     fn use_memoized(&self, old: &dyn Prop, new: &dyn Prop) -> bool;
 }
@@ -79,12 +177,30 @@ impl Component {
         children: &mut Children,
         ctx: &mut Context,
         dom: &mut Dom,
-        parent: Option<PrimitiveId>,
+        parent: Option<ParentData>,
     ) {
-        let new_children = self
-            .f
-            .call(&*self.props, Fctx::update(ctx.tx.clone(), id, dom.world));
+        let parent = ParentData {
+            mounted: id,
+            primitive: parent.and_then(|data| data.primitive),
+            depth: ParentData::depth_below(parent),
+        };
+        let new_children = call_component(
+            &*self.f,
+            &*self.props,
+            Fctx::update(
+                ctx.tx.clone(),
+                id,
+                ctx.slots.entry(id).or_default(),
+                children,
+                dom.world,
+            ),
+        )
+        .unwrap_or_else(panicked);
+        ctx.renders += 1;
+        let new_children = reported(new_children, self.f.fn_type_name(), dom.world);
+        ctx.chain.push(self.f.fn_type_name());
         ctx.diff_children(children, new_children, dom, parent);
+        ctx.chain.pop();
     }
 }
 
@@ -92,8 +208,12 @@ impl Component {
 struct ComponentTemplate {
     f: Box<dyn DynComponentFunc>,
     props: Box<dyn Prop>,
+    /// Set by the parent through [`Element::memo_with`], overriding the component's own policy.
+    memo: Option<MemoComparator>,
 }
 
+type MemoComparator = Arc<dyn Fn(&dyn Any, &dyn Any) -> bool + Send + Sync>;
+
 impl Clone for Box<dyn DynComponentFunc> {
     fn clone(&self) -> Self {
         (&**self).dyn_clone()
@@ -124,45 +244,299 @@ impl Clone for Box<dyn Prop> {
 #[derive(Clone)]
 enum ElementInner {
     Component(ComponentTemplate),
-    Primitive(PrimitiveData, Vec<Element>),
+    Primitive(Primitive, Vec<Element>),
 }
 
 #[derive(Clone)]
-pub struct Element(ElementInner, Option<Key>);
+pub struct Element(ElementInner, Option<Key>, Option<GridPos>);
+
+/// Where a child of [`grid`] goes, set with [`Element::grid_pos`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct GridPos {
+    column: usize,
+    row: usize,
+}
 
 impl Element {
-    pub fn with_key(self, key: Key) -> Self {
-        Self(self.0, Some(key))
+    /// Keys an element among its siblings. Elements are only ever matched with a previously
+    /// mounted sibling carrying the same key, so changing an element's key remounts it from
+    /// scratch, resetting the state of its whole subtree, instead of updating it.
+    pub fn with_key(mut self, key: Key) -> Self {
+        self.1 = Some(key);
+        self
+    }
+
+    /// Moves, rotates or scales a primitive without affecting layout, see [`UiTransform`].
+    pub fn transform(self, transform: UiTransform) -> Self {
+        self.map_primitive(|p| p.transform = Some(transform))
+    }
+
+    /// Runs `handler` whenever this button is clicked. The handler can update state through a
+    /// [`Setter`](crate::prelude::Setter), which re-renders as usual.
+    pub fn on_click(self, handler: impl Fn(&mut World) + Send + Sync + 'static) -> Self {
+        self.map_primitive(|p| p.on_click = Some(ClickHandler(Arc::new(handler))))
+    }
+
+    /// Lets the user select a text primitive by dragging over it and copy it with Ctrl+C.
+    pub fn selectable(self) -> Self {
+        self.map_primitive(|p| p.selectable = true)
+    }
+
+    /// Passes this element through `f`, for keeping builder chains in expression position.
+    pub fn apply(self, f: impl FnOnce(Self) -> Self) -> Self {
+        f(self)
+    }
+
+    /// Passes this element through `f` only when `cond` holds,
+    /// e.g. `e::node(children).apply_if(selected, |e| e.class("selected"))`.
+    pub fn apply_if(self, cond: bool, f: impl FnOnce(Self) -> Self) -> Self {
+        if cond {
+            f(self)
+        } else {
+            self
+        }
+    }
+
+    /// Places this element in the given cell, counted from zero, when it's a child of a [`grid`].
+    pub fn grid_pos(mut self, column: usize, row: usize) -> Self {
+        self.2 = Some(GridPos { column, row });
+        self
+    }
+
+    /// Skips re-rendering this component while `eq` considers its old and new props equal,
+    /// regardless of whether it was created with `e` or `memo_e`. `P` is the component's props
+    /// tuple; with any other type the component is never skipped. Primitives are left as is.
+    pub fn memo_with<P: 'static>(
+        mut self,
+        eq: impl Fn(&P, &P) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        if let ElementInner::Component(template) = &mut self.0 {
+            template.memo = Some(Arc::new(move |old, new| {
+                match (old.downcast_ref::<P>(), new.downcast_ref::<P>()) {
+                    (Some(old), Some(new)) => eq(old, new),
+                    _ => false,
+                }
+            }));
+        }
+        self
+    }
+
+    /// Remounts this element whenever `dep` changes, by keying it with a hash of `dep`.
+    /// Like any key, it must not collide with the keys of the element's siblings.
+    pub fn remount_on<H: Hash>(self, dep: H) -> Self {
+        let mut hasher = DefaultHasher::new();
+        dep.hash(&mut hasher);
+        self.with_key(Key(hasher.finish()))
+    }
+
+    /// Applies `f` to the primitive this element describes. Component elements are left as is.
+    fn map_primitive(mut self, f: impl FnOnce(&mut Primitive)) -> Self {
+        if let ElementInner::Primitive(primitive, _) = &mut self.0 {
+            f(primitive);
+        }
+        self
+    }
+
+    /// Sets the semantic role of a primitive, e.g. for inspectors and future accessibility support.
+    pub fn role(self, role: Role) -> Self {
+        self.map_primitive(|p| p.semantics.get_or_insert_with(Default::default).role = Some(role))
+    }
+
+    /// Sets the human readable label of a primitive.
+    pub fn label(self, label: impl Into<String>) -> Self {
+        let label = label.into();
+        self.map_primitive(|p| p.semantics.get_or_insert_with(Default::default).label = Some(label))
+    }
+
+    /// Sets the current value a primitive presents, such as a slider position.
+    pub fn value(self, value: impl Into<String>) -> Self {
+        let value = value.into();
+        self.map_primitive(|p| p.semantics.get_or_insert_with(Default::default).value = Some(value))
+    }
+
+    /// Fills a node with `color`, e.g. `e::styled_node(style, children).background(color)`.
+    /// Other primitives are left as is.
+    pub fn background(self, color: Color) -> Self {
+        self.map_primitive(|p| {
+            if let PrimitiveData::Node { color: old, .. } = &mut p.data {
+                *old = Some(color);
+            }
+        })
+    }
+
+    /// Applies a [`StyleSheet`](crate::prelude::StyleSheet) class to a node, which fills in
+    /// its style and color wherever they aren't set inline.
+    pub fn class(self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        self.map_primitive(|p| p.class = Some(name))
+    }
+
+    /// Compares the shape of two element trees, ignoring component props and the closures
+    /// primitives carry.
+    pub(crate) fn structurally_eq(&self, other: &Element) -> bool {
+        if self.1 != other.1 {
+            return false;
+        }
+        match (&self.0, &other.0) {
+            (ElementInner::Component(a), ElementInner::Component(b)) => {
+                a.f.fn_type_id() == b.f.fn_type_id()
+            }
+            (ElementInner::Primitive(a, a_children), ElementInner::Primitive(b, b_children)) => {
+                a.same_shape(b)
+                    && a_children.len() == b_children.len()
+                    && a_children
+                        .iter()
+                        .zip(b_children)
+                        .all(|(a, b)| a.structurally_eq(b))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Lets an optional child sit in a list of siblings, e.g. `e::node([header, footer.into()])`.
+/// `None` renders nothing but keeps its place, so the unkeyed siblings after it are still
+/// matched with themselves when it comes and goes, instead of with their neighbours.
+impl From<Option<Element>> for Element {
+    fn from(element: Option<Element>) -> Self {
+        element.unwrap_or_else(|| fragment(vec![]))
+    }
+}
+
+/// What the reconciler did, sent as a Bevy event when the `diff_events` feature is enabled.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiffEvent {
+    /// A component or primitive element was mounted, with the component's function name
+    /// or the kind of primitive.
+    Mounted(MountedId, &'static str),
+    Unmounted(MountedId),
+    PrimitiveUpdated(PrimitiveId),
+}
+
+#[cfg(feature = "diff_events")]
+fn emit(world: &mut World, event: DiffEvent) {
+    if let Some(mut events) = world.get_resource_mut::<bevy::app::Events<DiffEvent>>() {
+        events.send(event);
     }
 }
 
+#[cfg(not(feature = "diff_events"))]
+fn emit(_: &mut World, _: DiffEvent) {}
+
+/// When inserted as a resource in debug builds, every component is rendered twice on mount
+/// and the two outputs are asserted to be structurally identical. The effects of the first
+/// render are discarded, so only impure renders are caught.
+pub struct StrictMode;
+
 struct Mounted {
     inner: MountedInner,
     children: Children,
-    parent: Option<ParentPrimitiveData>,
+    parent: Option<ParentData>,
 }
 
 #[derive(Clone, Copy)]
-struct ParentPrimitiveData {
-    id: PrimitiveId,
-    cursor: usize,
+struct ParentData {
+    mounted: MountedId,
+    /// The closest primitive above, which the primitives of this subtree are children of.
+    primitive: Option<PrimitiveId>,
+    /// How many elements are above `mounted`.
+    depth: usize,
+}
+
+impl ParentData {
+    fn depth_below(parent: Option<ParentData>) -> usize {
+        parent.map_or(0, |data| data.depth + 1)
+    }
 }
 
-struct Children {
-    unkeyed: Vec<MountedId>,
-    keyed: HashMap<Key, MountedId>,
+/// Mounted children in the order they were rendered.
+pub(crate) struct Children {
+    entries: Vec<(Option<Key>, MountedId)>,
+}
+
+impl Children {
+    pub(crate) fn keys(&self) -> impl Iterator<Item = Option<Key>> + '_ {
+        self.entries.iter().map(|(key, _)| *key)
+    }
 }
 
 impl<'a> IntoIterator for &'a Children {
     type Item = &'a MountedId;
 
-    type IntoIter = std::iter::Chain<
-        core::slice::Iter<'a, MountedId>,
-        std::collections::hash_map::Values<'a, Key, MountedId>,
+    type IntoIter = std::iter::Map<
+        core::slice::Iter<'a, (Option<Key>, MountedId)>,
+        fn(&'a (Option<Key>, MountedId)) -> &'a MountedId,
     >;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.unkeyed.iter().chain(self.keyed.values())
+        self.entries.iter().map(|(_, id)| id)
+    }
+}
+
+/// The element that rendered this one. Unlike [`Mounted`], it's there from the moment the
+/// element's entity is spawned, so it can be followed during an element's first render.
+struct ParentLink(Option<MountedId>);
+
+/// Finds the closest element above `id` whose entity has a `T`.
+pub(crate) fn find_inherited<T: Component>(world: &World, id: MountedId) -> Option<Entity> {
+    let mut current = world.get::<ParentLink>(id.0)?.0;
+    while let Some(ancestor) = current {
+        if world.get::<T>(ancestor.0).is_some() {
+            return Some(ancestor.0);
+        }
+        current = world.get::<ParentLink>(ancestor.0)?.0;
+    }
+    None
+}
+
+/// Collects the components closest to `id`, which is `id` itself if it is a component.
+fn topmost_components(id: MountedId, world: &World, out: &mut Vec<MountedId>) {
+    let mounted = world.get::<Mounted>(id.0).unwrap();
+    match mounted.inner {
+        MountedInner::Component(_) => out.push(id),
+        MountedInner::Primitive(_) => {
+            for child in &mounted.children {
+                topmost_components(*child, world, out);
+            }
+        }
+    }
+}
+
+/// The first primitive rendered by `id`, looking through components.
+pub(crate) fn first_primitive(world: &World, id: MountedId) -> Option<PrimitiveId> {
+    let mounted = world.get::<Mounted>(id.0)?;
+    match mounted.inner {
+        MountedInner::Primitive(p) => Some(p),
+        MountedInner::Component(_) => mounted
+            .children
+            .into_iter()
+            .find_map(|child| first_primitive(world, *child)),
+    }
+}
+
+/// Collects the top-level primitives rendered by `children`, looking through components.
+fn primitive_order(children: &Children, world: &World, order: &mut Vec<Entity>) {
+    for id in children {
+        let mounted = world.get::<Mounted>(id.0).unwrap();
+        match &mounted.inner {
+            MountedInner::Primitive(p) => order.push(p.0),
+            MountedInner::Component(_) => primitive_order(&mounted.children, world, order),
+        }
+    }
+}
+
+/// Reorders the Bevy children of the primitive owning `id` to match the rendered element order.
+fn sync_enclosing_primitive(id: MountedId, world: &mut World) {
+    let mut parent = world.get::<Mounted>(id.0).unwrap().parent;
+    while let Some(data) = parent {
+        let mounted = world.get::<Mounted>(data.mounted.0).unwrap();
+        if let MountedInner::Primitive(p) = mounted.inner {
+            let mut order = Vec::new();
+            primitive_order(&mounted.children, world, &mut order);
+            Dom { world }.set_children_order(p, &order);
+            return;
+        }
+        parent = mounted.parent;
     }
 }
 
@@ -180,11 +554,59 @@ impl MountedInner {
     }
 }
 
+/// Decides whether the components subscribed to a resource need to re-render, run on every
+/// `process_messages`.
+pub(crate) type ResCheck = (fn(&World) -> bool, HashSet<MountedId>);
+
+/// Decides whether a component needs to re-render, run on every `process_messages`.
+pub(crate) type CmpCheck = Box<dyn FnMut(&mut World, MountedId) -> bool + Send>;
+
+/// Owns the mounted tree and drives rendering.
+///
+/// Mounting is depth first. A component finishes rendering and the world writes queued by its
+/// hooks are applied before any of its children render. Earlier siblings mount along with their
+/// whole subtree before later ones. State a parent shares through
+/// [`Fctx::use_broadcast_state`] is therefore visible to its children on their first render.
+/// A re-render follows the same order within the re-rendered subtree, but separately flagged
+/// components re-render in no particular order relative to each other.
 pub struct Context {
-    res_checks: HashMap<TypeId, (fn(&World) -> bool, Vec<MountedId>)>,
-    cmp_checks: HashMap<MountedId, Vec<fn(&mut World, MountedId) -> bool>>,
+    res_checks: HashMap<TypeId, ResCheck>,
+    cmp_checks: HashMap<MountedId, Vec<CmpCheck>>,
+    slots: HashMap<MountedId, Vec<Box<dyn HookSlot>>>,
     tx: Tx,
     rx: Rx,
+    pending: VecDeque<MountedId>,
+    frame_budget: Option<Duration>,
+    roots: Vec<MountedId>,
+    /// Set by [`Context::resume`] until the re-render it scheduled is done, so memoized
+    /// components catch up on what changed while paused too.
+    ignore_memo: bool,
+    /// Taken in order by the primitives mounting next to each other, see
+    /// [`Context::mount_root_adopting`].
+    adopting: VecDeque<Entity>,
+    max_depth: Option<usize>,
+    /// The components currently rendering their children, outermost first.
+    chain: Vec<&'static str>,
+    /// The component a re-render started from, whose ancestors aren't in `chain`.
+    rerendering: Option<MountedId>,
+    /// Components rendered since the last `process_messages` started, counting mounts made
+    /// between calls until the next call resets it.
+    renders: usize,
+    /// What `renders` was when the last `process_messages` finished, see [`Context::stats`].
+    last_render_count: usize,
+}
+
+/// Counters describing a [`Context`], for spotting render storms and leaks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ContextStats {
+    /// Mounted components, not counting primitives.
+    pub components: usize,
+    /// Resource subscriptions, one per component per resource it reads.
+    pub resource_checks: usize,
+    /// Checks run on every `process_messages` to decide whether a component re-renders.
+    pub component_checks: usize,
+    /// Components rendered, including first renders, by the last `process_messages`.
+    pub last_render_count: usize,
 }
 
 impl Context {
@@ -193,17 +615,129 @@ impl Context {
         Self {
             res_checks: HashMap::default(),
             cmp_checks: HashMap::default(),
+            slots: HashMap::default(),
             tx,
             rx,
+            pending: VecDeque::new(),
+            frame_budget: None,
+            roots: Vec::new(),
+            ignore_memo: false,
+            adopting: VecDeque::new(),
+            max_depth: Some(512),
+            chain: Vec::new(),
+            rerendering: None,
+            renders: 0,
+            last_render_count: 0,
         }
     }
+    /// Caps the time a single `process_messages` call spends re-rendering. Once the budget is
+    /// spent, the remaining flagged components are kept and rendered first on the next call.
+    ///
+    /// This trades hitches for tearing: while a large invalidation is spread over several
+    /// frames, parts of the UI show the new state and parts still show the old one.
+    /// At least one component is rendered per call, so progress is always made.
+    pub fn set_frame_budget(&mut self, budget: Option<Duration>) {
+        self.frame_budget = budget;
+    }
+    /// Caps how deep the mounted tree may grow, 512 elements by default. Children that would
+    /// go deeper are not mounted and a [`Diagnostic::TooDeep`] naming the components above them
+    /// is reported, so runaway recursion in a component shows up as a missing subtree rather
+    /// than a stack overflow. `None` removes the limit.
+    pub fn set_max_depth(&mut self, max_depth: Option<usize>) {
+        self.max_depth = max_depth;
+    }
     pub fn mount_root(&mut self, e: Element, dom: &mut Dom) -> MountedRootId {
-        MountedRootId(self.mount(e.0, dom, None))
+        let id = self.mount(e.0, dom, None);
+        self.roots.push(id);
+        MountedRootId(id)
+    }
+    /// Mounts a root whose topmost primitive takes over `existing` instead of spawning a new
+    /// entity, see [`Dom::adopt`]. The primitives it renders take over the children of
+    /// `existing` the same way, the first child primitive the first Bevy child and so on, all the
+    /// way down. Children left over once a primitive's children mounted are despawned, and
+    /// primitives past the last child spawn as usual. If the root renders several primitives,
+    /// only the first one adopts. Unmounting the root despawns `existing`.
+    pub fn mount_root_adopting(
+        &mut self,
+        e: Element,
+        existing: Entity,
+        dom: &mut Dom,
+    ) -> MountedRootId {
+        self.adopting = VecDeque::from(vec![existing]);
+        let id = self.mount_root(e, dom);
+        self.adopting.clear();
+        id
     }
     pub fn unmount_root(&mut self, id: MountedRootId, dom: &mut Dom) {
-        self.unmount(id.0, dom);
+        self.roots.retain(|root| *root != id.0);
+        self.unmount(id.0, dom, true);
+    }
+    /// Catches up after a pause. Effects queued while paused are applied in order without
+    /// flagging anything individually, then every component is scheduled for a single re-render,
+    /// which also picks up resource and component changes the skipped frames never looked at.
+    /// Memoized components re-render as well, as their props staying the same says nothing
+    /// about what they read changing.
+    pub fn resume(&mut self, world: &mut World) {
+        for resolver in self.rx.clone().try_iter() {
+            resolver.resolve(world, &mut Vec::new());
+        }
+        let mut components = Vec::new();
+        for root in &self.roots {
+            topmost_components(*root, world, &mut components);
+        }
+        self.schedule(components);
+        self.ignore_memo = true;
+    }
+    /// Re-renders the whole root `id` on the next [`Context::process_messages`], whether or not
+    /// anything it uses changed.
+    pub fn rerender_root(&mut self, id: MountedRootId, dom: &mut Dom) {
+        let mut components = Vec::new();
+        topmost_components(id.0, dom.world, &mut components);
+        self.schedule(components);
+    }
+    /// Renders `new` in place of the root `id`, updating whatever it shares with the current
+    /// tree the same way a re-render would. Returns the root's new id, which differs from `id`
+    /// when the topmost element changed type and the root was mounted from scratch.
+    pub fn replace_root(
+        &mut self,
+        id: MountedRootId,
+        new: Element,
+        dom: &mut Dom,
+    ) -> MountedRootId {
+        let mut root = id.0;
+        self.diff(&mut root, new, dom);
+        for existing in &mut self.roots {
+            if *existing == id.0 {
+                *existing = root;
+            }
+        }
+        MountedRootId(root)
+    }
+    fn schedule(&mut self, components: Vec<MountedId>) {
+        for id in components {
+            if !self.pending.contains(&id) {
+                self.pending.push_back(id);
+            }
+        }
     }
+    /// Applies the queued state changes and re-renders what they affect. Each component renders
+    /// at most once per call; changes queued while rendering, e.g. by effects, are picked up by
+    /// the next call.
     pub fn process_messages(&mut self, world: &mut World) {
+        // Left over if a render panicked halfway through the previous call.
+        self.chain.clear();
+        self.rerendering = None;
+        self.renders = 0;
+        if world.contains_resource::<Theme>() && world.is_resource_changed::<Theme>() {
+            Dom { world }.apply_theme();
+        }
+        if world.contains_resource::<StyleSheet>() && world.is_resource_changed::<StyleSheet>() {
+            Dom { world }.apply_style_sheet();
+        }
+        if world.contains_resource::<UiScale>() && world.is_resource_changed::<UiScale>() {
+            Dom { world }.apply_ui_scale();
+        }
+        Dom { world }.run_click_handlers();
         for (check, vec) in self.res_checks.values() {
             if check(&world) {
                 for &id in vec {
@@ -211,158 +745,294 @@ impl Context {
                 }
             }
         }
-        'outer: for (id, checks) in &self.cmp_checks {
+        for (id, checks) in &mut self.cmp_checks {
+            // Every check runs even once one fired, as checks update what they compare against.
+            let mut changed = false;
             for check in checks {
-                if check(world, *id) {
-                    self.tx.send(EffectResolver::Flag(*id)).unwrap();
-                    continue 'outer;
-                }
+                changed |= check(world, *id);
+            }
+            if changed {
+                self.tx.send(EffectResolver::Flag(*id)).unwrap();
             }
         }
-        let mut roots = HashSet::default();
-        let mut flagged = HashSet::default();
-        while !self.rx.is_empty() {
-            for resolver in self.rx.clone().try_iter() {
-                fn recursive(
-                    element: MountedId,
-                    roots: &mut HashSet<MountedId>,
-                    flagged: &mut HashSet<MountedId>,
-                    world: &World,
-                ) {
-                    for cid in &world.entity(element.0).get::<Mounted>().unwrap().children {
-                        roots.remove(cid);
-                        if !flagged.insert(*cid) {
-                            continue;
-                        }
-                        recursive(*cid, roots, flagged, world);
+        /// Flags everything below `element`, which then re-renders along with it. Walks with an
+        /// explicit stack, as flagged subtrees can be arbitrarily deep.
+        fn flag_subtree(
+            element: MountedId,
+            roots: &mut HashSet<MountedId>,
+            flagged: &mut HashSet<MountedId>,
+            world: &World,
+        ) {
+            let mut stack = vec![element];
+            while let Some(element) = stack.pop() {
+                for cid in &world.entity(element.0).get::<Mounted>().unwrap().children {
+                    roots.remove(cid);
+                    if flagged.insert(*cid) {
+                        stack.push(*cid);
                     }
                 }
+            }
+        }
 
-                match resolver.resolve(world) {
+        let start = Instant::now();
+        let mut roots = HashSet::default();
+        let mut flagged = HashSet::default();
+        let mut results = Vec::new();
+        // Every queued change is applied before anything renders, so a component flagged by
+        // several of them renders once, seeing all of them.
+        for resolver in self.rx.clone().try_iter() {
+            resolver.resolve(world, &mut results);
+            for result in results.drain(..) {
+                match result {
                     ResolveResult::Mounted(id) => {
-                        if flagged.contains(&id) {
+                        // Several sets of the same state flag it again, but it renders once.
+                        if flagged.contains(&id) || roots.contains(&id) {
                             continue;
                         }
                         roots.insert(id);
-                        recursive(id, &mut roots, &mut flagged, &world);
+                        flag_subtree(id, &mut roots, &mut flagged, &world);
                     }
                     ResolveResult::Resource(id) => {
-                        let ids = &*self.res_checks[&id].1;
+                        let ids = match self.res_checks.get(&id) {
+                            Some((_, ids)) => ids,
+                            None => continue,
+                        };
                         for id in ids.iter().copied() {
-                            if flagged.contains(&id) {
+                            if flagged.contains(&id) || roots.contains(&id) {
                                 continue;
                             }
                             roots.insert(id);
-                            recursive(id, &mut roots, &mut flagged, &world);
+                            flag_subtree(id, &mut roots, &mut flagged, &world);
                         }
                     }
                 };
             }
-            flagged.clear();
-            for rerender_root in roots.drain() {
-                let mut entity = world.entity_mut(rerender_root.0);
-                let mut mounted = entity.remove().unwrap();
-                let entity = entity.id();
+        }
+        // Roots left over from an exhausted frame budget re-render with their flagged ancestor.
+        self.pending
+            .retain(|id| !flagged.contains(id) && !roots.contains(id));
+        self.pending.extend(roots);
+        while let Some(rerender_root) = self.pending.pop_front() {
+            // A pending root may have been unmounted by an ancestor's render since it was flagged.
+            if !world
+                .get_entity(rerender_root.0)
+                .map_or(false, |e| e.contains::<Mounted>())
+            {
+                continue;
+            }
+            self.rerendering = Some(rerender_root);
+            with_mounted(world, rerender_root, |mounted, world| {
                 let Mounted {
                     ref mut inner,
                     ref mut children,
                     parent,
-                } = &mut mounted;
+                } = *mounted;
                 let c = inner.as_component().unwrap();
-                let mut dom = Dom { world, cursor: 0 };
-                if let Some(data) = &parent {
-                    dom.cursor = data.cursor;
-                    c.update(rerender_root, children, self, &mut dom, Some(data.id));
-                } else {
-                    c.update(rerender_root, children, self, &mut dom, None);
-                };
-                world.entity_mut(entity).insert(mounted);
+                c.update(rerender_root, children, self, &mut Dom { world }, parent);
+            });
+            sync_enclosing_primitive(rerender_root, world);
+            if let Some(budget) = self.frame_budget {
+                if start.elapsed() >= budget {
+                    break;
+                }
             }
         }
+        self.rerendering = None;
+        if self.pending.is_empty() {
+            self.ignore_memo = false;
+        }
+        self.last_render_count = self.renders;
+    }
+
+    /// The elements `id` rendered, in order. Empty if `id` isn't mounted.
+    pub fn children_of(&self, id: MountedId, world: &World) -> Vec<MountedId> {
+        world
+            .get::<Mounted>(id.0)
+            .map(|mounted| mounted.children.into_iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// The element that rendered `id`, or `None` for roots and elements that aren't mounted.
+    pub fn parent_of(&self, id: MountedId, world: &World) -> Option<MountedId> {
+        world
+            .get::<Mounted>(id.0)
+            .and_then(|mounted| mounted.parent)
+            .map(|data| data.mounted)
+    }
+
+    /// The first primitive `id` rendered, looking through components, e.g. to hand the entity a
+    /// component displays on to a picking system. `None` if it rendered no primitive or isn't
+    /// mounted.
+    pub fn primitive_of(&self, id: MountedId, world: &World) -> Option<PrimitiveId> {
+        first_primitive(world, id)
     }
 
     pub fn msg_count(&self) -> usize {
         self.rx.len()
     }
 
+    /// Counts what is mounted now, along with how many components the last
+    /// [`Context::process_messages`] rendered. Mounting or replacing roots between calls doesn't
+    /// add to the render count.
+    pub fn stats(&self) -> ContextStats {
+        ContextStats {
+            components: self.slots.len(),
+            resource_checks: self.res_checks.values().map(|(_, ids)| ids.len()).sum(),
+            component_checks: self.cmp_checks.values().map(Vec::len).sum(),
+            last_render_count: self.last_render_count,
+        }
+    }
+
+    /// Whether the children of `parent` would exceed the max depth, reporting it if so.
+    fn too_deep(&self, parent: ParentData, world: &World) -> bool {
+        match self.max_depth {
+            Some(max) if parent.depth + 1 >= max => {
+                let mut chain = self
+                    .rerendering
+                    .map_or_else(Vec::new, |id| components_above(id, world));
+                chain.extend(&self.chain);
+                let skip = chain.len().saturating_sub(8);
+                let chain = chain[skip..].to_vec();
+                report(world, Diagnostic::TooDeep { max, chain });
+                true
+            }
+            _ => false,
+        }
+    }
+
     fn mount(
         &mut self,
         element: ElementInner,
         dom: &mut Dom,
-        parent: Option<ParentPrimitiveData>,
+        parent: Option<ParentData>,
     ) -> MountedId {
         match element {
             ElementInner::Primitive(p, c) => {
-                let id = dom.mount_as_child(p, parent.map(|v| v.id));
-                let mut keyed = HashMap::default();
-                let mut unkeyed = Vec::new();
-                {
-                    let mut dom = Dom {
-                        world: dom.world,
-                        cursor: 0,
-                    };
+                let name = p.data.name();
+                let parent_primitive = parent.and_then(|data| data.primitive);
+                let (id, adoptable) = match self.adopting.pop_front() {
+                    Some(existing) => dom.adopt_detaching(existing, p, parent_primitive),
+                    None => (dom.mount_as_child(p, parent_primitive), Vec::new()),
+                };
+                let siblings = mem::replace(&mut self.adopting, adoptable.into());
+                let entity = dom
+                    .world
+                    .spawn()
+                    .insert(ParentLink(parent.map(|data| data.mounted)))
+                    .id();
+                let data = ParentData {
+                    mounted: MountedId(entity),
+                    primitive: Some(id),
+                    depth: ParentData::depth_below(parent),
+                };
+                let mut entries = Vec::new();
+                if !self.too_deep(data, dom.world) {
                     for element in c.into_iter() {
-                        let data = ParentPrimitiveData {
-                            id,
-                            cursor: dom.cursor,
-                        };
-                        if let Some(key) = element.1 {
-                            keyed.insert(key, self.mount(element.0, &mut dom, Some(data)));
-                        } else {
-                            unkeyed.push(self.mount(element.0, &mut dom, Some(data)));
-                        }
+                        entries.push((element.1, self.mount(element.0, dom, Some(data))));
                     }
                 }
-                let cursor = dom.cursor;
-                MountedId(
-                    dom.world
-                        .spawn()
-                        .insert(Mounted {
-                            inner: MountedInner::Primitive(id),
-                            children: Children { keyed, unkeyed },
-                            parent: parent.map(|data| ParentPrimitiveData {
-                                id: data.id,
-                                cursor,
-                            }),
-                        })
-                        .id(),
-                )
+                for unused in mem::replace(&mut self.adopting, siblings) {
+                    despawn_with_children_recursive(dom.world, unused);
+                }
+                dom.world.entity_mut(entity).insert(Mounted {
+                    inner: MountedInner::Primitive(id),
+                    children: Children { entries },
+                    parent,
+                });
+                emit(dom.world, DiffEvent::Mounted(MountedId(entity), name));
+                MountedId(entity)
             }
             ElementInner::Component(c) => {
-                let entity = dom.world.spawn().id();
-                let children = c.f.call(
+                let entity = dom
+                    .world
+                    .spawn()
+                    .insert(ParentLink(parent.map(|data| data.mounted)))
+                    .id();
+                let probe = if cfg!(debug_assertions) && dom.world.contains_resource::<StrictMode>()
+                {
+                    let (tx, _rx) = crossbeam_channel::unbounded();
+                    let mut slots = Vec::new();
+                    let probe = call_component(
+                        &*c.f,
+                        &*c.props,
+                        Fctx::render_first(
+                            tx,
+                            MountedId(entity),
+                            &mut HashMap::default(),
+                            &mut HashMap::default(),
+                            &mut slots,
+                            dom.world,
+                        )
+                        .discarding_effects(),
+                    )
+                    .unwrap_or_else(panicked);
+                    for slot in slots.into_iter().rev() {
+                        slot.teardown(dom.world);
+                    }
+                    Some(probe.into_iter().collect::<Vec<_>>())
+                } else {
+                    None
+                };
+                let children = call_component(
+                    &*c.f,
                     &*c.props,
                     Fctx::render_first(
                         self.tx.clone(),
                         MountedId(entity),
                         &mut self.res_checks,
                         &mut self.cmp_checks,
+                        self.slots.entry(MountedId(entity)).or_default(),
                         dom.world,
                     ),
-                );
-                let mut keyed = HashMap::default();
-                let mut unkeyed = Vec::new();
-                for element in children.into_iter() {
-                    let cursor = dom.cursor;
-                    let data = parent.map(|data| ParentPrimitiveData {
-                        id: data.id,
-                        cursor,
-                    });
-                    let mount_id = self.mount(element.0, dom, data);
-                    if let Some(key) = element.1 {
-                        keyed.insert(key, mount_id);
-                    } else {
-                        unkeyed.push(mount_id);
+                )
+                .unwrap_or_else(|message| {
+                    // Hooks past the panicking one never registered, so the ones before it would
+                    // be left half set up. The component stays mounted without any.
+                    self.forget_hooks(MountedId(entity), dom.world);
+                    panicked(message)
+                });
+                self.renders += 1;
+                let children = reported(children, c.f.fn_type_name(), dom.world);
+                let children = if let Some(probe) = probe {
+                    let children = children.into_iter().collect::<Vec<_>>();
+                    assert!(
+                        probe.len() == children.len()
+                            && probe
+                                .iter()
+                                .zip(&children)
+                                .all(|(a, b)| a.structurally_eq(b)),
+                        "StrictMode: `{}` rendered different elements when invoked twice, its render is impure",
+                        c.f.fn_type_name(),
+                    );
+                    ComponentOutput::Multiple(children)
+                } else {
+                    children
+                };
+                let data = ParentData {
+                    mounted: MountedId(entity),
+                    primitive: parent.and_then(|data| data.primitive),
+                    depth: ParentData::depth_below(parent),
+                };
+                let mut entries = Vec::new();
+                self.chain.push(c.f.fn_type_name());
+                if !self.too_deep(data, dom.world) {
+                    for element in children.into_iter() {
+                        entries.push((element.1, self.mount(element.0, dom, Some(data))));
                     }
                 }
+                self.chain.pop();
 
+                emit(
+                    dom.world,
+                    DiffEvent::Mounted(MountedId(entity), c.f.fn_type_name()),
+                );
                 let component = Component {
                     f: c.f,
                     props: c.props,
                 };
                 dom.world.entity_mut(entity).insert(Mounted {
                     inner: MountedInner::Component(component),
-                    children: Children { keyed, unkeyed },
+                    children: Children { entries },
                     parent,
                 });
                 MountedId(entity)
@@ -370,114 +1040,171 @@ impl Context {
         }
     }
 
-    fn unmount(&mut self, this: MountedId, dom: &mut Dom) {
+    /// Unmounts `this` and everything below it.
+    ///
+    /// Children are unmounted first, in the order they were rendered, so their hook teardowns,
+    /// such as effect cleanups, all run before their parent's. A component's own teardowns run in
+    /// reverse hook order while its entity still exists, so they can read its linked state.
+    ///
+    /// Detaching a primitive from its parent's Bevy children is a linear scan, so it is only
+    /// done when `detach` is set. Primitives nested in a despawned primitive never need it, and
+    /// within a diff the enclosing primitive's children are rewritten in one pass afterwards, so
+    /// clearing a large list stays linear instead of quadratic.
+    fn unmount(&mut self, this: MountedId, dom: &mut Dom, detach: bool) {
         let mut entity = dom.world.entity_mut(this.0);
         let Mounted {
             inner, children, ..
         } = entity.remove().unwrap();
+        let detach_children = detach && matches!(inner, MountedInner::Component(_));
         for &child in &children {
-            self.unmount(child, dom);
+            self.unmount(child, dom, detach_children);
         }
+        emit(dom.world, DiffEvent::Unmounted(this));
         match inner {
-            MountedInner::Primitive(id) => {
-                dom.remove(id);
-            }
+            MountedInner::Primitive(id) if detach => dom.remove(id),
+            MountedInner::Primitive(id) => dom.despawn(id),
             MountedInner::Component(_) => {
-                dom.world.despawn(this.0);
-                self.cmp_checks.remove(&this);
+                self.forget_hooks(this, dom.world);
+                self.slots.remove(&this);
+            }
+        }
+        // Primitives are tracked by an entity of their own, apart from the one they display on.
+        dom.world.despawn(this.0);
+    }
+
+    /// Tears down the hooks of component `id` and drops the checks they registered.
+    fn forget_hooks(&mut self, id: MountedId, world: &mut World) {
+        if let Some(slots) = self.slots.get_mut(&id) {
+            for slot in slots.drain(..).rev() {
+                slot.teardown(world);
             }
         }
+        self.cmp_checks.remove(&id);
+        // Resources nobody reads anymore aren't checked at all.
+        self.res_checks.retain(|_, (_, subscribers)| {
+            subscribers.remove(&id);
+            !subscribers.is_empty()
+        });
     }
 
     fn diff(&mut self, id: &mut MountedId, other: Element, dom: &mut Dom) {
-        let mut entity = dom.world.entity_mut(id.0);
-        let mut mounted = entity.remove().unwrap();
-        let entity = entity.id();
-        let Mounted {
-            ref mut inner,
-            ref mut children,
-            ref mut parent,
-        } = &mut mounted;
-        let parent = *parent;
-        match (inner, other.0) {
-            (MountedInner::Primitive(p_id), ElementInner::Primitive(new, new_children)) => {
-                dom.diff_primitive(*p_id, new);
-                {
-                    let mut dom = Dom {
-                        world: dom.world,
-                        cursor: 0,
-                    };
+        let this = *id;
+        let (parent, replaced) = with_mounted(dom.world, this, |mounted, world| {
+            let dom = &mut Dom { world };
+            let Mounted {
+                ref mut inner,
+                ref mut children,
+                parent,
+            } = *mounted;
+            let replaced = match (inner, other.0) {
+                (MountedInner::Primitive(p_id), ElementInner::Primitive(new, new_children)) => {
+                    let p_id = *p_id;
+                    dom.diff_primitive(p_id, new);
+                    emit(dom.world, DiffEvent::PrimitiveUpdated(p_id));
                     self.diff_children(
                         children,
                         ComponentOutput::Multiple(new_children),
-                        &mut dom,
-                        Some(*p_id),
+                        dom,
+                        ParentData {
+                            mounted: this,
+                            primitive: Some(p_id),
+                            depth: ParentData::depth_below(parent),
+                        },
                     );
+                    let mut order = Vec::new();
+                    primitive_order(children, dom.world, &mut order);
+                    dom.set_children_order(p_id, &order);
+                    None
                 }
-                dom.world.entity_mut(entity).insert(mounted);
-            }
-            (MountedInner::Component(ref mut old), ElementInner::Component(new)) => {
-                if old.f.fn_type_id() == new.f.fn_type_id() {
-                    if !old.f.use_memoized(&*old.props, &*new.props) {
-                        old.update(*id, children, self, dom, parent.map(|v| v.id));
-                    }
-                    dom.world.entity_mut(entity).insert(mounted);
-                } else {
-                    for child in children.unkeyed.drain(..) {
-                        self.unmount(child, dom);
+                (MountedInner::Component(ref mut old), ElementInner::Component(new)) => {
+                    if old.f.fn_type_id() == new.f.fn_type_id() {
+                        let memoized = !self.ignore_memo
+                            && match &new.memo {
+                                Some(eq) => eq((*old.props).as_any(), (*new.props).as_any()),
+                                None => old.f.use_memoized(&*old.props, &*new.props),
+                            };
+                        // Kept even when memoized, so a later re-render of the component on its
+                        // own, e.g. for its state, sees what its parent last passed.
+                        old.f = new.f;
+                        old.props = new.props;
+                        if !memoized {
+                            old.update(this, children, self, dom, parent);
+                        }
+                        None
+                    } else {
+                        Some(ElementInner::Component(new))
                     }
-                    dom.world.entity_mut(entity).insert(mounted);
-                    self.unmount(*id, dom);
-                    *id = self.mount(ElementInner::Component(new), dom, parent);
                 }
-            }
-            (_, new) => {
-                dom.world.entity_mut(entity).insert(mounted);
-                self.unmount(*id, dom);
-                *id = self.mount(new, dom, parent);
-            }
+                (_, new) => Some(new),
+            };
+            (parent, replaced)
+        });
+        if let Some(new) = replaced {
+            self.unmount(this, dom, false);
+            *id = self.mount(new, dom, parent);
         }
     }
 
+    /// Matches `new` against the mounted children and updates them in place.
+    ///
+    /// A keyed child always keeps its entities when its siblings are reordered or removed, and
+    /// only its position among its parent's Bevy children changes. Anything tracked by entity,
+    /// like interaction state or a scroll offset, follows the item to its new position.
     fn diff_children(
         &mut self,
         old: &mut Children,
         new: ComponentOutput,
         dom: &mut Dom,
-        parent: Option<PrimitiveId>,
+        parent: ParentData,
     ) {
-        let mut unkeyed = Vec::new();
+        let new = if self.too_deep(parent, dom.world) {
+            ComponentOutput::None
+        } else {
+            new
+        };
+        // Old children stay in their slots until reused, so the leftovers are unmounted
+        // in the order they were rendered.
+        let mut slots = old
+            .entries
+            .drain(..)
+            .map(|(key, id)| (key, Some(id)))
+            .collect::<Vec<_>>();
+        // Unkeyed children are matched in order, the first new one with the first old one.
+        let mut unkeyed = VecDeque::new();
         let mut keyed = HashMap::default();
+        for (index, (key, _)) in slots.iter().enumerate() {
+            if let Some(key) = key {
+                keyed.insert(*key, index);
+            } else {
+                unkeyed.push_back(index);
+            }
+        }
+        let mut seen = HashSet::default();
         for element in new {
-            let data = parent.map(|id| ParentPrimitiveData {
-                id,
-                cursor: dom.cursor,
-            });
-            if let Some(key) = element.1 {
-                if let Some(mut old_id) = old.keyed.remove(&key) {
-                    self.diff(&mut old_id, element, dom);
-                    keyed.insert(key, old_id);
-                } else {
-                    keyed.insert(key, self.mount(element.0, dom, data));
+            let key = element.1;
+            if let Some(key) = key {
+                if !seen.insert(key) {
+                    report(dom.world, Diagnostic::DuplicateKey { key });
                 }
+            }
+            let reused = if let Some(key) = key {
+                keyed.remove(&key)
             } else {
-                if let Some(mut old_id) = old.unkeyed.pop() {
-                    self.diff(&mut old_id, element, dom);
-                    unkeyed.push(old_id);
-                } else {
-                    unkeyed.push(self.mount(element.0, dom, data));
-                }
+                unkeyed.pop_front()
             }
+            .and_then(|index| slots[index].1.take());
+            let id = if let Some(mut old_id) = reused {
+                self.diff(&mut old_id, element, dom);
+                old_id
+            } else {
+                self.mount(element.0, dom, Some(parent))
+            };
+            old.entries.push((key, id));
         }
-        for removed in std::mem::replace(&mut old.unkeyed, unkeyed)
-            .into_iter()
-            .chain(
-                std::mem::replace(&mut old.keyed, keyed)
-                    .into_iter()
-                    .map(|(_, v)| v),
-            )
-        {
-            self.unmount(removed, dom);
+        for (_, removed) in slots {
+            if let Some(removed) = removed {
+                self.unmount(removed, dom, false);
+            }
         }
     }
 }
@@ -488,16 +1215,17 @@ macro_rules! impl_functions {
         impl<Func, Out, $($ident,)*> ComponentFunc<($($ident,)*), Out> for Func
         where
             $($ident: Any + Send + Sync + Clone,)*
-            Func: Fn(Fctx, $(&$ident,)*) -> Out + Copy + Send + Sync + 'static,
+            Func: Fn(Fctx, $(&$ident,)*) -> Out + Clone + Send + Sync + 'static,
             ComponentOutput: From<Out>,
             Out: 'static,
         {
             fn e(&self, props: ($($ident,)*)) -> Element {
                 Element(ElementInner::Component(ComponentTemplate {
                     // Why must I have such horrible double-boxing :(
-                    f: Box::new(Box::new(*self) as Box<dyn ComponentFunc<($($ident,)*), Out>>),
+                    f: Box::new(Box::new(self.clone()) as Box<dyn ComponentFunc<($($ident,)*), Out>>),
                     props: Box::new(props),
-                }), None)
+                    memo: None,
+                }), None, None)
             }
 
             fn call(&self, ($($ident,)*): &($($ident,)*), ctx: Fctx) -> ComponentOutput {
@@ -508,8 +1236,12 @@ macro_rules! impl_functions {
                 std::any::TypeId::of::<Func>()
             }
 
+            fn fn_type_name(&self) -> &'static str {
+                std::any::type_name::<Func>()
+            }
+
             fn dyn_clone(&self) -> Box<dyn ComponentFunc<($($ident,)*), Out>> {
-                Box::new(*self)
+                Box::new(self.clone())
             }
 
             fn memo_e(&self, props: ($($ident,)*)) -> Element
@@ -519,10 +1251,11 @@ macro_rules! impl_functions {
                 Element(ElementInner::Component(ComponentTemplate {
                     // Why must I have such horrible double-boxing :(
                     f: Box::new(MemoizableComponentFunc(
-                        Box::new(*self) as Box<dyn ComponentFunc<($($ident,)*), Out>>
+                        Box::new(self.clone()) as Box<dyn ComponentFunc<($($ident,)*), Out>>
                     )),
                     props: Box::new(props),
-                }), None)
+                    memo: None,
+                }), None, None)
             }
         }
 
@@ -538,6 +1271,7 @@ macro_rules! impl_functions {
             }
             fn call(&self, _: &($($ident,)*), _: Fctx) -> ComponentOutput { unreachable!() }
             fn fn_type_id(&self) -> TypeId { unreachable!() }
+            fn fn_type_name(&self) -> &'static str { unreachable!() }
             fn dyn_clone(&self) -> Box<dyn ComponentFunc<($($ident,)*), ()>> { unreachable!() }
         }
     };
@@ -557,6 +1291,43 @@ impl_functions!(A, B, C, D, E, F, G, H, I, J);
 impl_functions!(A, B, C, D, E, F, G, H, I, J, K);
 impl_functions!(A, B, C, D, E, F, G, H, I, J, K, L);
 
+/// Marks the [`ComponentFunc`] impl taking a single prop that isn't wrapped in a tuple.
+pub struct SingleProp<Out>(PhantomData<fn() -> Out>);
+
+impl<Func, Out, A> ComponentFunc<A, SingleProp<Out>> for Func
+where
+    A: Any + Send + Sync + Clone,
+    Func: Fn(Fctx, &A) -> Out + Clone + Send + Sync + 'static,
+    ComponentOutput: From<Out>,
+    Out: 'static,
+{
+    fn e(&self, prop: A) -> Element {
+        ComponentFunc::<(A,), Out>::e(self, (prop,))
+    }
+
+    fn memo_e(&self, prop: A) -> Element
+    where
+        A: PartialEq,
+    {
+        ComponentFunc::<(A,), Out>::memo_e(self, (prop,))
+    }
+
+    // `e` and `memo_e` hand the prop over to the `(A,)` impl, so the template never holds this
+    // one and nothing calls these.
+    fn call(&self, _: &A, _: Fctx) -> ComponentOutput {
+        unreachable!()
+    }
+    fn fn_type_id(&self) -> TypeId {
+        unreachable!()
+    }
+    fn fn_type_name(&self) -> &'static str {
+        unreachable!()
+    }
+    fn dyn_clone(&self) -> Box<dyn ComponentFunc<A, SingleProp<Out>>> {
+        unreachable!()
+    }
+}
+
 impl<P: Any, M: 'static> DynComponentFunc for Box<dyn ComponentFunc<P, M>> {
     fn call(&self, p: &dyn Prop, ctx: Fctx) -> ComponentOutput {
         (&**self).call(p.as_any().downcast_ref().unwrap(), ctx)
@@ -564,6 +1335,9 @@ impl<P: Any, M: 'static> DynComponentFunc for Box<dyn ComponentFunc<P, M>> {
     fn fn_type_id(&self) -> TypeId {
         (&**self).fn_type_id()
     }
+    fn fn_type_name(&self) -> &'static str {
+        (&**self).fn_type_name()
+    }
 
     fn dyn_clone(&self) -> Box<dyn DynComponentFunc> {
         Box::new((&**self).dyn_clone())
@@ -583,24 +1357,111 @@ impl<P: PartialEq + Any, M: 'static> DynComponentFunc for MemoizableComponentFun
     fn fn_type_id(&self) -> TypeId {
         (&*self.0).fn_type_id()
     }
+    fn fn_type_name(&self) -> &'static str {
+        (&*self.0).fn_type_name()
+    }
 
     fn dyn_clone(&self) -> Box<dyn DynComponentFunc> {
         Box::new((&*self.0).dyn_clone())
     }
 
     fn use_memoized(&self, old: &dyn Prop, new: &dyn Prop) -> bool {
-        old.as_any()
-            .downcast_ref::<P>()
-            .zip(new.as_any().downcast_ref::<P>())
-            .map(|(a, b)| a == b)
-            .unwrap_or(false)
+        match (
+            old.as_any().downcast_ref::<P>(),
+            new.as_any().downcast_ref::<P>(),
+        ) {
+            (Some(old), Some(new)) => old == new,
+            // Only reachable if a component is compared with props of another type.
+            _ => {
+                #[cfg(feature = "memo_checks")]
+                bevy::log::warn!(
+                    "bevy_hooked: the props of `{}` aren't a `{}`, re-rendering it",
+                    self.fn_type_name(),
+                    std::any::type_name::<P>()
+                );
+                false
+            }
+        }
     }
 }
 
 pub enum ComponentOutput {
+    /// Renders nothing. The component itself stays mounted in its place among its siblings.
     None,
     Single(Element),
     Multiple(Vec<Element>),
+    /// A component returned an `Err`, see [`Diagnostic::RenderFailed`].
+    Failed(String),
+}
+
+/// The names of the components `id` was rendered by, outermost first.
+fn components_above(id: MountedId, world: &World) -> Vec<&'static str> {
+    let mut chain = Vec::new();
+    let mut current = world
+        .get::<Mounted>(id.0)
+        .and_then(|mounted| mounted.parent);
+    while let Some(parent) = current {
+        let mounted = match world.get::<Mounted>(parent.mounted.0) {
+            Some(mounted) => mounted,
+            None => break,
+        };
+        if let MountedInner::Component(c) = &mounted.inner {
+            chain.push(c.f.fn_type_name());
+        }
+        current = mounted.parent;
+    }
+    chain.reverse();
+    chain
+}
+
+/// Runs `f` with the [`Mounted`] of `id` taken out of the world, so rendering below it can borrow
+/// the world, and puts it back afterwards. A panic in `f` is passed on only after that, so a
+/// caller catching it is left with a tree that still has every `Mounted` in place.
+fn with_mounted<R>(
+    world: &mut World,
+    id: MountedId,
+    f: impl FnOnce(&mut Mounted, &mut World) -> R,
+) -> R {
+    let mut mounted = world.entity_mut(id.0).remove::<Mounted>().unwrap();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| f(&mut mounted, world)));
+    world.entity_mut(id.0).insert(mounted);
+    result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
+
+/// Renders a component. With the `panic_recovery` feature, a panicking render fails instead,
+/// rendering nothing and leaving the rest of the tree alive. The `Err` holds the panic message.
+#[cfg(feature = "panic_recovery")]
+fn call_component(
+    f: &dyn DynComponentFunc,
+    props: &dyn Prop,
+    ctx: Fctx,
+) -> Result<ComponentOutput, String> {
+    panic::catch_unwind(AssertUnwindSafe(|| f.call(props, ctx)))
+        .map_err(|payload| panic_message(&*payload))
+}
+
+#[cfg(not(feature = "panic_recovery"))]
+fn call_component(
+    f: &dyn DynComponentFunc,
+    props: &dyn Prop,
+    ctx: Fctx,
+) -> Result<ComponentOutput, String> {
+    Ok(f.call(props, ctx))
+}
+
+fn panicked(message: String) -> ComponentOutput {
+    ComponentOutput::Failed(format!("panicked: {}", message))
+}
+
+/// Reports `output` if the component failed, which then renders nothing.
+fn reported(output: ComponentOutput, component: &'static str, world: &World) -> ComponentOutput {
+    match output {
+        ComponentOutput::Failed(error) => {
+            report(world, Diagnostic::RenderFailed { component, error });
+            ComponentOutput::None
+        }
+        output => output,
+    }
 }
 
 impl IntoIterator for ComponentOutput {
@@ -610,7 +1471,9 @@ impl IntoIterator for ComponentOutput {
 
     fn into_iter(self) -> Self::IntoIter {
         match self {
-            ComponentOutput::None => ComponentOutputIterator::OptionIterator(None.into_iter()),
+            ComponentOutput::None | ComponentOutput::Failed(_) => {
+                ComponentOutputIterator::OptionIterator(None.into_iter())
+            }
             ComponentOutput::Single(s) => {
                 ComponentOutputIterator::OptionIterator(Some(s).into_iter())
             }
@@ -654,16 +1517,1557 @@ impl From<Option<Element>> for ComponentOutput {
         v.map(|v| Self::Single(v)).unwrap_or(ComponentOutput::None)
     }
 }
-pub fn node(children: impl Into<Vec<Element>>) -> Element {
-    Element(
-        ElementInner::Primitive(PrimitiveData::Node, children.into()),
+
+/// Lets components return early with `?`. The error is reported to
+/// [`HookedDiagnostics`](crate::prelude::HookedDiagnostics) and the component renders nothing,
+/// unmounting whatever it rendered before.
+impl<T, E: fmt::Display> From<Result<T, E>> for ComponentOutput
+where
+    ComponentOutput: From<T>,
+{
+    fn from(v: Result<T, E>) -> Self {
+        match v {
+            Ok(v) => v.into(),
+            Err(e) => ComponentOutput::Failed(e.to_string()),
+        }
+    }
+}
+fn primitive(data: PrimitiveData, children: Vec<Element>) -> Element {
+    Element(
+        ElementInner::Primitive(
+            Primitive {
+                data,
+                semantics: None,
+                class: None,
+                selectable: false,
+                transform: None,
+                scroll: None,
+                on_click: None,
+            },
+            children,
+        ),
+        None,
         None,
     )
 }
 
+pub fn node(children: impl Into<Vec<Element>>) -> Element {
+    primitive(
+        PrimitiveData::Node {
+            style: None,
+            color: None,
+        },
+        children.into(),
+    )
+}
+
+/// A node laid out with `style` instead of the default column, e.g. a row of buttons or a
+/// centering wrapper.
+pub fn styled_node(style: Style, children: impl Into<Vec<Element>>) -> Element {
+    primitive(
+        PrimitiveData::Node {
+            style: Some(style),
+            color: None,
+        },
+        children.into(),
+    )
+}
+
+/// Lays `children` out left to right.
+pub fn row(children: impl Into<Vec<Element>>) -> Element {
+    styled_node(
+        Style {
+            flex_direction: FlexDirection::Row,
+            ..Default::default()
+        },
+        children,
+    )
+}
+
+/// Lays `children` out top to bottom, like [`node`] does unless a class styles it.
+pub fn column(children: impl Into<Vec<Element>>) -> Element {
+    styled_node(
+        Style {
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::FlexStart,
+            ..Default::default()
+        },
+        children,
+    )
+}
+
+/// Fills the free space of its parent and stacks `children` top to bottom in its middle.
+pub fn center(children: impl Into<Vec<Element>>) -> Element {
+    styled_node(
+        Style {
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            align_self: AlignSelf::Stretch,
+            flex_grow: 1.,
+            ..Default::default()
+        },
+        children,
+    )
+}
+
+pub fn image(texture: Handle<Texture>) -> Element {
+    primitive(
+        PrimitiveData::Image {
+            texture,
+            style: None,
+        },
+        vec![],
+    )
+}
+
+/// An image laid out with `style` instead of being sized to its texture.
+pub fn styled_image(style: Style, texture: Handle<Texture>) -> Element {
+    primitive(
+        PrimitiveData::Image {
+            texture,
+            style: Some(style),
+        },
+        vec![],
+    )
+}
+
+/// A single line text field showing `value`. Typing into it while it is focused calls
+/// `on_change` with the edited text, which is expected to end up rendered back as `value`,
+/// e.g. through state the callback sets.
+pub fn text_input(value: impl Into<String>, on_change: Callback<String>) -> Element {
+    primitive(
+        PrimitiveData::TextInput {
+            value: value.into(),
+            on_change: Some(on_change),
+        },
+        vec![],
+    )
+}
+
+pub fn button(children: impl Into<Vec<Element>>) -> Element {
+    primitive(PrimitiveData::Button, children.into())
+}
+
+/// An invisible node that grows to fill the free space of its parent.
+pub fn spacer() -> Element {
+    primitive(
+        PrimitiveData::Node {
+            style: Some(Style {
+                flex_grow: 1.,
+                ..Default::default()
+            }),
+            color: Some(Color::NONE),
+        },
+        vec![],
+    )
+}
+
+/// A thin line separating its siblings. It is `thickness` pixels long along the
+/// parent's main axis and stretches across the other one, so it works in rows and columns.
+pub fn divider(thickness: f32, color: Color) -> Element {
+    primitive(
+        PrimitiveData::Node {
+            style: Some(Style {
+                flex_basis: Val::Px(thickness),
+                flex_grow: 0.,
+                flex_shrink: 0.,
+                align_self: AlignSelf::Stretch,
+                ..Default::default()
+            }),
+            color: Some(color),
+        },
+        vec![],
+    )
+}
+
+/// Two panes side by side, sharing the width equally.
+pub fn split(panes: [Element; 2]) -> Element {
+    columns(panes)
+}
+
+/// `N` panes side by side, sharing the width equally.
+pub fn columns<const N: usize>(panes: [Element; N]) -> Element {
+    let panes = Vec::from(panes)
+        .into_iter()
+        .map(|pane| {
+            primitive(
+                PrimitiveData::Node {
+                    style: Some(Style {
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::FlexStart,
+                        flex_grow: 1.,
+                        flex_basis: Val::Px(0.),
+                        ..Default::default()
+                    }),
+                    color: Some(Color::NONE),
+                },
+                vec![pane],
+            )
+        })
+        .collect();
+    primitive(
+        PrimitiveData::Node {
+            style: Some(Style {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Stretch,
+                ..Default::default()
+            }),
+            color: Some(Color::NONE),
+        },
+        panes,
+    )
+}
+
+/// Layout of a [`grid`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GridConfig {
+    pub columns: usize,
+    /// The minimum number of rows. More are added when the children don't fit.
+    pub rows: usize,
+    /// Space between cells, in pixels.
+    pub gap: f32,
+}
+
+/// Lays `children` out in equally sized cells. Children with a [`Element::grid_pos`] inside the
+/// grid take that cell, the others fill the free cells in order, row by row. A grid never has
+/// more rows than the larger of `rows` and its number of children, so positions further down
+/// are placed in order too rather than adding empty rows up to them.
+///
+/// Bevy's UI has no native grid, so this is built from rows of flex nodes. Moving a child to
+/// another row remounts it.
+pub fn grid(config: GridConfig, children: impl Into<Vec<Element>>) -> Element {
+    let columns = config.columns.max(1);
+    let children = children.into();
+    let max_rows = config.rows.max(children.len());
+    let mut cells: Vec<Option<Element>> = Vec::new();
+    let mut auto = Vec::new();
+    for child in children {
+        match child.2 {
+            Some(pos) if pos.column < columns && pos.row < max_rows => {
+                let index = pos.row * columns + pos.column;
+                if index >= cells.len() {
+                    cells.resize_with(index + 1, || None);
+                }
+                if cells[index].is_none() {
+                    cells[index] = Some(child);
+                } else {
+                    auto.push(child);
+                }
+            }
+            _ => auto.push(child),
+        }
+    }
+    let mut free = 0;
+    for child in auto {
+        while free < cells.len() && cells[free].is_some() {
+            free += 1;
+        }
+        if free == cells.len() {
+            cells.push(None);
+        }
+        cells[free] = Some(child);
+    }
+    let rows = config.rows.max((cells.len() + columns - 1) / columns);
+    cells.resize_with(rows * columns, || None);
+
+    let mut cells = cells.into_iter();
+    let mut grid_rows = Vec::new();
+    for row in 0..rows {
+        if row > 0 {
+            grid_rows.push(divider(config.gap, Color::NONE));
+        }
+        let mut row_cells = Vec::new();
+        for column in 0..columns {
+            if column > 0 {
+                row_cells.push(divider(config.gap, Color::NONE));
+            }
+            let cell = cells.next().unwrap();
+            row_cells.push(primitive(
+                PrimitiveData::Node {
+                    style: Some(Style {
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::FlexStart,
+                        flex_grow: 1.,
+                        flex_basis: Val::Px(0.),
+                        ..Default::default()
+                    }),
+                    color: Some(Color::NONE),
+                },
+                cell.into_iter().collect(),
+            ));
+        }
+        grid_rows.push(primitive(
+            PrimitiveData::Node {
+                style: Some(Style {
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::Stretch,
+                    flex_grow: 1.,
+                    flex_basis: Val::Px(0.),
+                    ..Default::default()
+                }),
+                color: Some(Color::NONE),
+            },
+            row_cells,
+        ));
+    }
+    primitive(
+        PrimitiveData::Node {
+            style: Some(Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Stretch,
+                ..Default::default()
+            }),
+            color: Some(Color::NONE),
+        },
+        grid_rows,
+    )
+}
+
+/// A label followed by the field it describes, vertically centered on one row.
+pub fn labeled_field([label, field]: [Element; 2]) -> Element {
+    primitive(
+        PrimitiveData::Node {
+            style: Some(Style {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            }),
+            color: Some(Color::NONE),
+        },
+        vec![label, field],
+    )
+}
+
+/// Places `child` at `position` relative to the closest primitive above, taking it out of the
+/// flex flow so its siblings lay out as if it wasn't there. For tooltips and menus.
+pub fn overlay(position: Rect<Val>, child: Element) -> Element {
+    primitive(
+        PrimitiveData::Node {
+            style: Some(Style {
+                position_type: PositionType::Absolute,
+                position,
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::FlexStart,
+                ..Default::default()
+            }),
+            color: Some(Color::NONE),
+        },
+        vec![child],
+    )
+}
+
+/// A viewport of `size` clipping `child`, which is scrolled by `controller`'s offset. The
+/// controller comes from [`Fctx::use_scroll`] in the component rendering this. The mouse wheel
+/// scrolls the innermost area under the cursor.
+pub fn scroll(controller: &ScrollController, size: Size<Val>, child: Element) -> Element {
+    let content = primitive(
+        PrimitiveData::Node {
+            style: Some(Style {
+                flex_shrink: 0.,
+                position: Rect {
+                    top: Val::Px(controller.offset),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::FlexStart,
+                ..Default::default()
+            }),
+            color: Some(Color::NONE),
+        },
+        vec![child],
+    );
+    primitive(
+        PrimitiveData::Node {
+            style: Some(Style {
+                size,
+                overflow: Overflow::Hidden,
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Stretch,
+                ..Default::default()
+            }),
+            color: Some(Color::NONE),
+        },
+        vec![content],
+    )
+    .map_primitive(|p| p.scroll = Some(ScrollViewport(controller.owner)))
+}
+
+/// A primitive set up by `spawn`, which inserts whatever bundles it needs into the primitive's
+/// entity, e.g. a node with a custom material. `spawn` runs again whenever the primitive is
+/// diffed, so it should insert rather than assume a fresh entity.
+pub fn raw(
+    spawn: impl Fn(&mut EntityMut) + Send + Sync + 'static,
+    children: impl Into<Vec<Element>>,
+) -> Element {
+    primitive(
+        PrimitiveData::Raw(RawSpawn(Arc::new(spawn))),
+        children.into(),
+    )
+}
+
+/// Renders the component `f` with `props`, the same as `f.e(props)`. Handy when building a
+/// `Vec<Element>` out of different component types, e.g. mapping settings to their widgets.
+/// Give such children keys: a keyed child whose component type changes between renders is
+/// remounted with fresh state rather than updated.
+pub fn any<P, M>(f: impl ComponentFunc<P, M>, props: P) -> Element {
+    f.e(props)
+}
+
 pub fn text(text: impl Into<String>) -> Element {
-    Element(
-        ElementInner::Primitive(PrimitiveData::Text(text.into()), vec![]),
-        None,
+    primitive(
+        PrimitiveData::Text {
+            value: text.into(),
+            style: Default::default(),
+        },
+        vec![],
     )
 }
+
+/// Text at its own font `size`, before [`UiScale`], and `color` instead of the [`Theme`]'s.
+pub fn styled_text(text: impl Into<String>, size: f32, color: Color) -> Element {
+    primitive(
+        PrimitiveData::Text {
+            value: text.into(),
+            style: TextOverrides {
+                size: Some(size),
+                color: Some(color),
+                font: None,
+            },
+        },
+        vec![],
+    )
+}
+
+/// Defers building and mounting `child` until `show` is first true. From then on the child stays
+/// mounted, keeping its state, and is only hidden while `show` is false.
+/// See [`lazy_unmounting`] to unmount it on hide instead.
+pub fn lazy(show: bool, child: impl Fn() -> Element + Send + Sync + 'static) -> Element {
+    lazy_component.e((show, true, LazyChild(Arc::new(child))))
+}
+
+/// Like [`lazy`], but unmounts the child whenever `show` is false.
+pub fn lazy_unmounting(show: bool, child: impl Fn() -> Element + Send + Sync + 'static) -> Element {
+    lazy_component.e((show, false, LazyChild(Arc::new(child))))
+}
+
+/// Renders `child`, but shows `fallback` in its place while any component below it reports
+/// pending with [`Fctx::use_suspense`]. The child stays mounted and hidden in the meantime,
+/// so it can keep loading.
+pub fn suspense(fallback: Element, child: Element) -> Element {
+    suspense_component.e((fallback, child))
+}
+
+fn suspense_component(ctx: Fctx, fallback: &Element, child: &Element) -> Vec<Element> {
+    let (boundary, _) = ctx.use_linked_state(SuspenseBoundary::default);
+    let suspended = !boundary.pending.is_empty();
+    let mut children = vec![primitive(
+        PrimitiveData::Node {
+            style: Some(Style {
+                display: if suspended {
+                    Display::None
+                } else {
+                    Display::Flex
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::FlexStart,
+                ..Default::default()
+            }),
+            color: Some(Color::NONE),
+        },
+        vec![child.clone()],
+    )
+    .with_key(Key(0))];
+    if suspended {
+        children.push(fallback.clone().with_key(Key(1)));
+    }
+    children
+}
+
+/// Wraps an element cached by [`Fctx::use_element`] in a component that is skipped for as long
+/// as the cache hands out the same element, so the subtree isn't diffed at all.
+pub(crate) fn shared_element(element: Arc<Element>) -> Element {
+    let (key, grid_pos) = (element.1, element.2);
+    let wrapper = shared_element_component
+        .e((SharedElement(element),))
+        .memo_with(|(old,): &(SharedElement,), (new,): &(SharedElement,)| {
+            Arc::ptr_eq(&old.0, &new.0)
+        });
+    Element(wrapper.0, key, grid_pos)
+}
+
+#[derive(Clone)]
+struct SharedElement(Arc<Element>);
+
+fn shared_element_component(_: Fctx, element: &SharedElement) -> Element {
+    (*element.0).clone()
+}
+
+/// Groups `children` into a region of their own, such as the header, body and footer of a card
+/// component taking each as an `Element` or `Vec<Element>` prop. Children are only matched
+/// against the previous children of the same slot, so one slot growing or shrinking doesn't
+/// disturb the others. Slots add nothing to the layout.
+pub fn slot(key: Key, children: Vec<Element>) -> Element {
+    fragment(children).with_key(key)
+}
+
+/// Renders `children` in place, as if they were children of the enclosing primitive, e.g. to
+/// return several siblings from a component where a single element is expected.
+pub fn fragment(children: impl Into<Vec<Element>>) -> Element {
+    fragment_component.e((children.into(),))
+}
+
+fn fragment_component(_: Fctx, children: &Vec<Element>) -> Vec<Element> {
+    children.clone()
+}
+
+/// Renders each of `items`, keyed by `key_of`, so reordering or removing items moves or drops
+/// their mounted elements instead of re-rendering the ones that shifted.
+pub fn keyed_list<T>(
+    items: impl IntoIterator<Item = T>,
+    key_of: impl Fn(&T) -> u64,
+    render: impl Fn(&T) -> Element,
+) -> Vec<Element> {
+    items
+        .into_iter()
+        .map(|item| render(&item).with_key(Key(key_of(&item))))
+        .collect()
+}
+
+/// A prop that renders part of a component's output from a value the component supplies, e.g. a
+/// list taking how to render each row. Never equal to another, so memoized components taking
+/// one re-render whenever their parent does.
+pub struct RenderProp<T>(Arc<dyn Fn(T) -> Element + Send + Sync>);
+
+impl<T> RenderProp<T> {
+    pub fn new(f: impl Fn(T) -> Element + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    pub fn render(&self, value: T) -> Element {
+        (self.0)(value)
+    }
+}
+
+impl<T> Clone for RenderProp<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> PartialEq for RenderProp<T> {
+    fn eq(&self, _: &Self) -> bool {
+        false
+    }
+}
+
+/// An event handler passed down as a prop, e.g. a button's `on_click: Callback<()>`. Clones
+/// compare equal, so a memoized child doesn't re-render as long as its parent hands it the same
+/// callback, for instance one created once with [`Fctx::use_memo`].
+pub struct Callback<Args = ()>(Arc<dyn Fn(Args) + Send + Sync>);
+
+impl<Args> Callback<Args> {
+    pub fn new(f: impl Fn(Args) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    pub fn call(&self, args: Args) {
+        (self.0)(args)
+    }
+}
+
+impl<Args> Clone for Callback<Args> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Args> fmt::Debug for Callback<Args> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Callback")
+    }
+}
+
+impl<Args> PartialEq for Callback<Args> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::as_ptr(&self.0) as *const () == Arc::as_ptr(&other.0) as *const ()
+    }
+}
+
+#[derive(Clone)]
+struct LazyChild(Arc<dyn Fn() -> Element + Send + Sync>);
+
+fn lazy_component(
+    ctx: Fctx,
+    show: &bool,
+    keep_mounted: &bool,
+    child: &LazyChild,
+) -> Option<Element> {
+    let shown_before = ctx.use_plain(
+        || false,
+        |shown| {
+            let before = *shown;
+            *shown |= *show;
+            before
+        },
+    );
+    if !*show && !(*keep_mounted && shown_before) {
+        return None;
+    }
+    if !*keep_mounted {
+        return Some((child.0)());
+    }
+    Some(primitive(
+        PrimitiveData::Node {
+            style: Some(Style {
+                display: if *show { Display::Flex } else { Display::None },
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::FlexStart,
+                ..Default::default()
+            }),
+            color: Some(Color::NONE),
+        },
+        vec![(child.0)()],
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use bevy::{
+        prelude::{BuildWorldChildren, Entity, NodeBundle, World},
+        text::Text,
+        ui::{AlignItems, FlexDirection, JustifyContent, Style},
+    };
+
+    use crate::prelude::*;
+
+    thread_local! {
+        static RENDERS: RefCell<Vec<&'static str>> = RefCell::new(Vec::new());
+    }
+
+    fn log(name: &'static str) {
+        RENDERS.with(|renders| renders.borrow_mut().push(name));
+    }
+
+    fn take_log() -> Vec<&'static str> {
+        RENDERS.with(|renders| renders.borrow_mut().drain(..).collect())
+    }
+
+    struct Greeting(&'static str);
+
+    fn leaf(ctx: Fctx, name: &&'static str) -> Element {
+        log(*name);
+        let greeting = ctx.use_inherited_state::<Greeting>().unwrap().0;
+        e::text(format!("{} {}", name, greeting))
+    }
+
+    fn branch(_: Fctx, name: &&'static str, leaf_name: &&'static str) -> Element {
+        log(*name);
+        e::node([leaf.e(*leaf_name)])
+    }
+
+    fn tree(ctx: Fctx) -> Element {
+        log("tree");
+        ctx.use_broadcast_state(Greeting("hi"));
+        e::node([branch.e(("a", "a1")), branch.e(("b", "b1"))])
+    }
+
+    #[test]
+    fn mounting_is_depth_first() {
+        let mut harness = TestHarness::new();
+        harness.mount(tree.e(()));
+        assert_eq!(take_log(), ["tree", "a", "a1", "b", "b1"]);
+        // Broadcast state is in place for the first render of the children.
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Node\n    Text \"a1 hi\"\n  Node\n    Text \"b1 hi\"\n",
+        );
+    }
+
+    fn text_entity(harness: &mut TestHarness, value: &str) -> Entity {
+        harness
+            .world
+            .query::<(Entity, &Text)>()
+            .iter(&harness.world)
+            .find(|(_, text)| text.sections[0].value == value)
+            .map(|(entity, _)| entity)
+            .unwrap()
+    }
+
+    fn rotating_list(ctx: Fctx) -> Element {
+        let (order, set_order) = ctx.use_state(|| vec![1u64, 2, 3]);
+        e::node([
+            e::button([e::text("Rotate")])
+                .on_click(move |_| set_order.set(|order| order.rotate_left(1))),
+            e::node(e::keyed_list(
+                order.iter().copied(),
+                |n| *n,
+                |n| e::text(n.to_string()),
+            )),
+        ])
+    }
+
+    #[test]
+    fn keyed_reorders_move_bevy_children() {
+        let mut harness = TestHarness::new();
+        harness.mount(rotating_list.e(()));
+        let one = text_entity(&mut harness, "1");
+        harness.click("Rotate");
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Button\n    Text \"Rotate\"\n  Node\n    Text \"2\"\n    Text \"3\"\n    Text \"1\"\n",
+        );
+        assert_eq!(text_entity(&mut harness, "1"), one);
+    }
+
+    fn item(_: Fctx, n: &u64) -> Element {
+        e::node([e::text(n.to_string())])
+    }
+
+    fn shrinking_list(ctx: Fctx) -> Element {
+        let (items, set_items) = ctx.use_state(|| vec![1u64, 2, 3]);
+        e::node([
+            e::button([e::text("Remove")]).on_click(move |_| {
+                set_items.set(|items| {
+                    items.remove(1);
+                })
+            }),
+            e::node(
+                items
+                    .iter()
+                    .map(|n| item.e(*n).with_key(Key(*n)))
+                    .collect::<Vec<_>>(),
+            ),
+        ])
+    }
+
+    #[test]
+    fn removing_a_middle_child_unmounts_only_it() {
+        let mut harness = TestHarness::new();
+        harness.mount(shrinking_list.e(()));
+        let one = text_entity(&mut harness, "1");
+        let two = text_entity(&mut harness, "2");
+        let three = text_entity(&mut harness, "3");
+        harness.click("Remove");
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Button\n    Text \"Remove\"\n  Node\n    Node\n      Text \"1\"\n    Node\n      Text \"3\"\n",
+        );
+        assert_eq!(text_entity(&mut harness, "1"), one);
+        assert_eq!(text_entity(&mut harness, "3"), three);
+        assert!(harness.world.get_entity(two).is_none());
+    }
+
+    fn opening(_: Fctx) -> Element {
+        e::text("Opening")
+    }
+
+    fn middle(_: Fctx) -> Element {
+        e::text("Middle")
+    }
+
+    fn replacement(_: Fctx) -> Element {
+        e::text("Replacement")
+    }
+
+    fn closing(_: Fctx) -> Element {
+        e::text("Closing")
+    }
+
+    fn unkeyed_row(ctx: Fctx) -> Element {
+        let (replaced, set_replaced) = ctx.use_state(|| false);
+        e::node([
+            e::button([e::text("Replace")])
+                .on_click(move |_| set_replaced.set(|replaced| *replaced = true)),
+            opening.e(()),
+            if *replaced {
+                replacement.e(())
+            } else {
+                middle.e(())
+            },
+            closing.e(()),
+        ])
+    }
+
+    #[test]
+    fn unkeyed_children_diff_against_the_same_position() {
+        let mut harness = TestHarness::new();
+        harness.mount(unkeyed_row.e(()));
+        let opening = text_entity(&mut harness, "Opening");
+        let middle = text_entity(&mut harness, "Middle");
+        let closing = text_entity(&mut harness, "Closing");
+        harness.click("Replace");
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Button\n    Text \"Replace\"\n  Text \"Opening\"\n  Text \"Replacement\"\n  \
+             Text \"Closing\"\n",
+        );
+        assert_eq!(text_entity(&mut harness, "Opening"), opening);
+        assert_eq!(text_entity(&mut harness, "Closing"), closing);
+        assert!(harness.world.get_entity(middle).is_none());
+    }
+
+    fn shrinking_keyed_list(ctx: Fctx) -> Element {
+        let (items, set_items) = ctx.use_state(|| vec![1u64, 2, 3]);
+        e::node([
+            e::button([e::text("Remove")]).on_click(move |_| {
+                set_items.set(|items| {
+                    items.remove(1);
+                })
+            }),
+            e::node(e::keyed_list(
+                items.iter().copied(),
+                |n| *n,
+                |n| e::text(n.to_string()),
+            )),
+        ])
+    }
+
+    #[test]
+    fn keyed_list_reuses_the_remaining_items() {
+        let mut harness = TestHarness::new();
+        harness.mount(shrinking_keyed_list.e(()));
+        let one = text_entity(&mut harness, "1");
+        let two = text_entity(&mut harness, "2");
+        let three = text_entity(&mut harness, "3");
+        harness.click("Remove");
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Button\n    Text \"Remove\"\n  Node\n    Text \"1\"\n    Text \"3\"\n",
+        );
+        assert_eq!(text_entity(&mut harness, "1"), one);
+        assert_eq!(text_entity(&mut harness, "3"), three);
+        assert!(harness.world.get_entity(two).is_none());
+    }
+
+    fn rotating_inputs(ctx: Fctx) -> Element {
+        let (order, set_order) = ctx.use_state(|| vec![1u64, 2, 3]);
+        e::node([
+            e::button([e::text("Rotate")])
+                .on_click(move |_| set_order.set(|order| order.rotate_left(1))),
+            e::node(e::keyed_list(
+                order.iter().copied(),
+                |n| *n,
+                |n| e::text_input(n.to_string(), Callback::new(|_| {})),
+            )),
+        ])
+    }
+
+    #[test]
+    fn focus_follows_keyed_reorders() {
+        let mut harness = TestHarness::new();
+        harness.mount(rotating_inputs.e(()));
+        let two = text_entity(&mut harness, "2");
+        harness.world.insert_resource(Focused(Some(two)));
+        harness.click("Rotate");
+        assert_eq!(text_entity(&mut harness, "2"), two);
+        assert_eq!(
+            *harness.world.get_resource::<Focused>().unwrap(),
+            Focused(Some(two))
+        );
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Button\n    Text \"Rotate\"\n  Node\n    TextInput \"2\"\n    \
+             TextInput \"3\"\n    TextInput \"1\"\n",
+        );
+    }
+
+    fn counted(ctx: Fctx) -> Element {
+        let renders = ctx.use_ref(|| 0);
+        renders.with(|renders| *renders += 1);
+        e::text(format!("Rendered {} times", renders.get()))
+    }
+
+    #[test]
+    fn replace_root_swaps_the_tree() {
+        let mut harness = TestHarness::new();
+        let root = harness.mount(e::node([e::text("Old")]));
+        let root = harness.replace_root(root, e::node([e::text("New"), e::text("Added")]));
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Text \"New\"\n  Text \"Added\"\n"
+        );
+        let root = harness.replace_root(root, e::text("Text"));
+        assert_eq!(harness.snapshot(), "Text \"Text\"\n");
+        harness.replace_root(root, counted.e(()));
+        assert_eq!(harness.snapshot(), "Text \"Rendered 1 times\"\n");
+    }
+
+    #[test]
+    fn rerender_root_renders_unchanged_components() {
+        let mut harness = TestHarness::new();
+        let root = harness.mount(counted.e(()));
+        harness.rerender_root(root);
+        harness.tick();
+        assert_eq!(harness.snapshot(), "Text \"Rendered 2 times\"\n");
+    }
+
+    struct Score(u32);
+
+    fn score(ctx: Fctx) -> Element {
+        e::text(ctx.use_resource::<Score>().0.to_string())
+    }
+
+    fn scoreboard(_: Fctx) -> Element {
+        e::node([score.e(()), score.e(()), counted.e(())])
+    }
+
+    #[test]
+    fn render_count_matches_flagged_components() {
+        let mut harness = TestHarness::new();
+        harness.world.insert_resource(Score(0));
+        harness.mount(scoreboard.e(()));
+        harness.tick();
+        harness.world.get_resource_mut::<Score>().unwrap().0 = 1;
+        harness.tick();
+        let stats = harness.context().stats();
+        assert_eq!(stats.last_render_count, 2);
+        assert_eq!(stats.components, 4);
+        assert_eq!(stats.resource_checks, 2);
+        // Mounting between ticks isn't counted as part of the last tick.
+        harness.mount(scoreboard.e(()));
+        assert_eq!(harness.context().stats().last_render_count, 2);
+        harness.tick();
+        assert_eq!(harness.context().stats().last_render_count, 0);
+    }
+
+    fn memoized_score(_: Fctx) -> Element {
+        e::node([score.memo_e(())])
+    }
+
+    #[test]
+    fn resuming_renders_memoized_components() {
+        let mut harness = TestHarness::new();
+        harness.world.insert_resource(Score(0));
+        harness.mount(memoized_score.e(()));
+        harness.tick();
+        // A paused frame: the change is never checked before trackers are cleared.
+        harness.world.get_resource_mut::<Score>().unwrap().0 = 1;
+        harness.world.clear_trackers();
+        harness.resume();
+        harness.tick();
+        assert_eq!(harness.snapshot(), "Node\n  Text \"1\"\n");
+    }
+
+    struct Clicks(u32);
+
+    thread_local! {
+        static KEPT_SETTER: RefCell<Option<Setter<Clicks>>> = RefCell::new(None);
+    }
+
+    fn keeps_its_setter(ctx: Fctx) -> Element {
+        let (clicks, set_clicks) = ctx.use_linked_state(|| Clicks(0));
+        KEPT_SETTER.with(|kept| *kept.borrow_mut() = Some(set_clicks));
+        e::text(clicks.0.to_string())
+    }
+
+    fn hides_it(ctx: Fctx) -> Element {
+        let (shown, set_shown) = ctx.use_state(|| true);
+        e::node([
+            e::button([e::text("Hide")]).on_click(move |_| set_shown.set(|shown| *shown = false)),
+            if *shown {
+                keeps_its_setter.e(())
+            } else {
+                e::fragment(Vec::new())
+            },
+        ])
+    }
+
+    #[test]
+    fn setting_unmounted_state_is_reported() {
+        let mut harness = TestHarness::new();
+        let reported = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = reported.clone();
+        harness
+            .world
+            .insert_resource(HookedDiagnostics::new(move |diagnostic| {
+                sink.lock().unwrap().push(diagnostic)
+            }));
+        harness.mount(hides_it.e(()));
+        harness.click("Hide");
+        let setter = KEPT_SETTER.with(|kept| kept.borrow_mut().take()).unwrap();
+        setter.set(|mut clicks| clicks.0 += 1);
+        harness.tick();
+        assert!(matches!(
+            reported.lock().unwrap()[..],
+            [Diagnostic::UnmountedUpdate { .. }]
+        ));
+    }
+
+    #[test]
+    fn far_grid_positions_dont_add_rows() {
+        let mut harness = TestHarness::new();
+        let config = GridConfig {
+            columns: 2,
+            rows: 1,
+            gap: 0.,
+        };
+        harness.mount(e::grid(
+            config,
+            vec![e::text("a").grid_pos(0, 1_000_000), e::text("b")],
+        ));
+        // One row: a cell, the gap between columns, and another cell.
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Node\n    Node\n      Text \"a\"\n    Node\n    Node\n      Text \"b\"\n",
+        );
+    }
+
+    #[test]
+    fn adopting_takes_over_the_children_too() {
+        let mut harness = TestHarness::new();
+        let world = &mut harness.world;
+        let children = (0..3)
+            .map(|_| world.spawn().insert_bundle(NodeBundle::default()).id())
+            .collect::<Vec<_>>();
+        let existing = world
+            .spawn()
+            .insert_bundle(NodeBundle::default())
+            .push_children(&children)
+            .id();
+        harness.mount_adopting(e::node([e::text("a"), e::text("b")]), existing);
+        assert_eq!(harness.snapshot(), "Node\n  Text \"a\"\n  Text \"b\"\n");
+        let adopted = harness.world.get::<bevy::prelude::Children>(existing);
+        assert_eq!(adopted.unwrap().to_vec(), &children[..2]);
+        assert!(harness.world.get_entity(children[2]).is_none());
+    }
+
+    fn panel(ctx: Fctx) -> Element {
+        let (open, set_open) = ctx.use_state(|| false);
+        e::node([
+            e::button([e::text("Open")]).on_click(move |_| set_open.set(|open| *open = true)),
+            e::lazy(*open, || {
+                log("content");
+                e::text("Content")
+            }),
+        ])
+    }
+
+    #[test]
+    fn lazy_children_are_built_once_shown() {
+        let mut harness = TestHarness::new();
+        harness.mount(panel.e(()));
+        assert!(take_log().is_empty());
+        harness.click("Open");
+        // The lazy component re-rendered with the `show` its parent passed this time.
+        assert_eq!(take_log(), ["content"]);
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Button\n    Text \"Open\"\n  Node\n    Text \"Content\"\n",
+        );
+    }
+
+    fn nest(_: Fctx, levels: &u32) -> Element {
+        match levels {
+            0 => e::text("Bottom"),
+            levels => nest.e(levels - 1),
+        }
+    }
+
+    fn deepening(ctx: Fctx) -> Element {
+        let (deep, set_deep) = ctx.use_state(|| false);
+        e::node([
+            e::button([e::text("Deeper")]).on_click(move |_| set_deep.set(|deep| *deep = true)),
+            if *deep {
+                nest.e(3)
+            } else {
+                e::fragment(Vec::new())
+            },
+        ])
+    }
+
+    fn wrapper(_: Fctx) -> Element {
+        deepening.e(())
+    }
+
+    #[test]
+    fn too_deep_names_the_components_above_a_re_render() {
+        let mut harness = TestHarness::new();
+        let reported = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = reported.clone();
+        harness
+            .world
+            .insert_resource(HookedDiagnostics::new(move |diagnostic| {
+                sink.lock().unwrap().push(diagnostic)
+            }));
+        harness.context().set_max_depth(Some(6));
+        harness.mount(wrapper.e(()));
+        harness.click("Deeper");
+        let chain = match &reported.lock().unwrap()[..] {
+            [Diagnostic::TooDeep { chain, .. }] => chain.clone(),
+            reported => panic!("expected a single TooDeep, got {:?}", reported),
+        };
+        let names = chain
+            .iter()
+            .map(|name| name.rsplit("::").next().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["wrapper", "deepening", "nest", "nest", "nest"]);
+    }
+
+    #[cfg(feature = "panic_recovery")]
+    fn broken(ctx: Fctx) -> Element {
+        ctx.use_resource::<Score>();
+        ctx.use_linked_state(|| Greeting("never inserted"));
+        panic!("broken")
+    }
+
+    #[cfg(feature = "panic_recovery")]
+    fn with_broken(_: Fctx) -> Element {
+        e::node([score.e(()), broken.e(()), score.e(())])
+    }
+
+    #[cfg(feature = "panic_recovery")]
+    #[test]
+    fn panicking_components_leave_their_siblings_rendered() {
+        let mut harness = TestHarness::new();
+        harness.world.insert_resource(Score(0));
+        harness.mount(with_broken.e(()));
+        assert_eq!(harness.snapshot(), "Node\n  Text \"0\"\n  Text \"0\"\n");
+        // The broken component's subscription and state check are gone, only its siblings'
+        // remain, and checking them doesn't look for the state it never inserted.
+        let stats = harness.context().stats();
+        assert_eq!(stats.components, 4);
+        assert_eq!(stats.resource_checks, 2);
+        assert_eq!(stats.component_checks, 0);
+        harness.world.get_resource_mut::<Score>().unwrap().0 = 1;
+        harness.tick();
+        assert_eq!(harness.snapshot(), "Node\n  Text \"1\"\n  Text \"1\"\n");
+    }
+
+    #[cfg(not(feature = "panic_recovery"))]
+    thread_local! {
+        static PANICKED: std::cell::Cell<bool> = std::cell::Cell::new(false);
+    }
+
+    #[cfg(not(feature = "panic_recovery"))]
+    fn panics_once(ctx: Fctx) -> Element {
+        let (broken, set_broken) = ctx.use_state(|| false);
+        if *broken && !PANICKED.with(|panicked| panicked.replace(true)) {
+            panic!("panics_once");
+        }
+        e::button([e::text("Break")]).on_click(move |_| set_broken.set(|broken| *broken = true))
+    }
+
+    #[cfg(not(feature = "panic_recovery"))]
+    fn around_panics(ctx: Fctx) -> Element {
+        let (count, set_count) = ctx.use_state(|| 0u32);
+        e::node([
+            panics_once.e(()),
+            e::button([e::text(count.to_string())])
+                .on_click(move |_| set_count.set(|count| *count += 1)),
+        ])
+    }
+
+    #[cfg(not(feature = "panic_recovery"))]
+    #[test]
+    fn caught_render_panics_leave_the_tree_mounted() {
+        let mut harness = TestHarness::new();
+        harness.mount(around_panics.e(()));
+        let clicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            harness.click("Break");
+        }));
+        assert!(clicked.is_err());
+        harness.world.clear_trackers();
+        // Re-rendering the parent diffs the component that panicked, which is still mounted.
+        harness.click("0");
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Button\n    Text \"Break\"\n  Button\n    Text \"1\"\n",
+        );
+    }
+
+    fn labeled(_: Fctx, label: &&'static str, value: &u32) -> Element {
+        e::text(format!("{} {}", label, value))
+    }
+
+    fn props_of_every_arity(_: Fctx) -> Element {
+        e::node([
+            counted.e(()),
+            nest.e(0),
+            nest.e((0,)),
+            nest.memo_e(0),
+            labeled.e(("Score", 3)),
+            labeled.memo_e(("Score", 3)),
+        ])
+    }
+
+    #[test]
+    fn props_can_be_passed_as_zero_one_or_many() {
+        let mut harness = TestHarness::new();
+        harness.mount(props_of_every_arity.e(()));
+        assert!(nest.e(0).structurally_eq(&nest.e((0,))));
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Text \"Rendered 1 times\"\n  Text \"Bottom\"\n  Text \"Bottom\"\n  Text \"Bottom\"\n  \
+             Text \"Score 3\"\n  Text \"Score 3\"\n",
+        );
+    }
+
+    fn total(_: Fctx, items: &Vec<u32>) -> Element {
+        log("total");
+        e::text(items.iter().sum::<u32>().to_string())
+    }
+
+    fn totals(ctx: Fctx) -> Element {
+        let (extra, set_extra) = ctx.use_state(|| 0);
+        let (_, set_renders) = ctx.use_state(|| 0);
+        e::node([
+            e::button([e::text("Rerender")]).on_click(move |_| set_renders.set(|n| *n += 1)),
+            e::button([e::text("Add")]).on_click(move |_| set_extra.set(|n| *n += 1)),
+            total.memo_e((vec![1, 2, *extra],)),
+        ])
+    }
+
+    #[test]
+    fn memoized_vec_props_skip_equal_renders() {
+        let mut harness = TestHarness::new();
+        harness.mount(totals.e(()));
+        assert_eq!(take_log(), ["total"]);
+        harness.click("Rerender");
+        assert!(take_log().is_empty());
+        harness.click("Add");
+        assert_eq!(take_log(), ["total"]);
+        assert!(harness.snapshot().ends_with("Text \"4\"\n"));
+    }
+
+    struct Volume(u32);
+
+    fn volume(ctx: Fctx) -> Element {
+        e::text(ctx.use_resource::<Volume>().0.to_string())
+    }
+
+    #[test]
+    fn resource_subscriptions_are_kept_once_and_dropped_on_unmount() {
+        let mut harness = TestHarness::new();
+        harness.world.insert_resource(Volume(3));
+        let root = harness.mount(e::node([volume.e(())]));
+        for _ in 0..5 {
+            harness.rerender_root(root);
+            harness.tick();
+        }
+        assert_eq!(harness.context().stats().resource_checks, 1);
+        harness.replace_root(root, e::node(Vec::new()));
+        assert_eq!(harness.context().stats().resource_checks, 0);
+    }
+
+    fn muted(world: &World) -> bool {
+        world.get_resource::<Volume>().unwrap().0 == 0
+    }
+
+    fn volume_control(ctx: Fctx) -> Element {
+        let muted = ctx.use_condition(muted);
+        e::node([
+            e::text(if muted { "Muted" } else { "Playing" }),
+            volume.e(()),
+        ])
+    }
+
+    /// The mounted components and the resource and component checks registered for them.
+    fn tracked(harness: &mut TestHarness) -> (usize, usize, usize) {
+        let stats = harness.context().stats();
+        (
+            stats.components,
+            stats.resource_checks,
+            stats.component_checks,
+        )
+    }
+
+    #[test]
+    fn unmounting_a_subtree_drops_its_checks() {
+        let mut harness = TestHarness::new();
+        harness.world.insert_resource(Volume(3));
+        let root = harness.mount(e::node([volume_control.e(()), volume_control.e(())]));
+        assert_eq!(tracked(&mut harness), (4, 2, 2));
+        harness.replace_root(root, e::node([volume_control.e(())]));
+        assert_eq!(tracked(&mut harness), (2, 1, 1));
+        harness.replace_root(root, e::node(Vec::new()));
+        assert_eq!(tracked(&mut harness), (0, 0, 0));
+    }
+
+    fn flipping(ctx: Fctx) -> Element {
+        let (vertical, set_vertical) = ctx.use_state(|| false);
+        let children = vec![e::button([e::text("Flip")])
+            .on_click(move |_| set_vertical.set(|vertical| *vertical = !*vertical))];
+        if *vertical {
+            e::column(children)
+        } else {
+            e::row(children)
+        }
+    }
+
+    /// The entities and styles of the mounted nodes.
+    fn node_styles(harness: &mut TestHarness) -> Vec<(Entity, Style)> {
+        harness
+            .world
+            .query::<(Entity, &PrimitiveKind, &Style)>()
+            .iter(&harness.world)
+            .filter(|(_, kind, _)| **kind == PrimitiveKind::Node)
+            .map(|(entity, _, style)| (entity, style.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn switching_row_and_column_restyles_the_node() {
+        let mut harness = TestHarness::new();
+        harness.mount(flipping.e(()));
+        let (node, style) = node_styles(&mut harness).pop().unwrap();
+        assert_eq!(style.flex_direction, FlexDirection::Row);
+        harness.click("Flip");
+        let (flipped, style) = node_styles(&mut harness).pop().unwrap();
+        assert_eq!(flipped, node);
+        assert_eq!(style.flex_direction, FlexDirection::ColumnReverse);
+
+        let mut harness = TestHarness::new();
+        harness.mount(e::center(Vec::new()));
+        let (_, style) = node_styles(&mut harness).pop().unwrap();
+        assert_eq!(style.flex_direction, FlexDirection::ColumnReverse);
+        assert_eq!(style.align_items, AlignItems::Center);
+        assert_eq!(style.justify_content, JustifyContent::Center);
+    }
+
+    fn tally(ctx: Fctx, label: &&'static str) -> Element {
+        let (count, set_count) = ctx.use_state(|| 0);
+        e::button([e::text(format!("{} {}", label, count))])
+            .on_click(move |_| set_count.set(|count| *count += 1))
+    }
+
+    fn editor(_: Fctx) -> Element {
+        e::node([tally.e("Outer"), e::node([tally.e("Inner")])])
+    }
+
+    fn profile(ctx: Fctx) -> Element {
+        let (user, set_user) = ctx.use_state(|| 1);
+        let (_, set_renders) = ctx.use_state(|| 0);
+        e::node([
+            e::button([e::text("Switch")]).on_click(move |_| set_user.set(|user| *user += 1)),
+            e::button([e::text("Refresh")]).on_click(move |_| set_renders.set(|n| *n += 1)),
+            editor.e(()).remount_on(*user),
+        ])
+    }
+
+    #[test]
+    fn remount_on_resets_the_state_below_it() {
+        let mut harness = TestHarness::new();
+        harness.mount(profile.e(()));
+        harness.click("Outer 0");
+        harness.click("Inner 0");
+        harness.click("Refresh");
+        assert!(harness.snapshot().contains("Text \"Outer 1\""));
+        assert!(harness.snapshot().contains("Text \"Inner 1\""));
+        harness.click("Switch");
+        assert!(harness.snapshot().contains("Text \"Outer 0\""));
+        assert!(harness.snapshot().contains("Text \"Inner 0\""));
+    }
+
+    fn grouped(ctx: Fctx) -> Element {
+        let (expanded, set_expanded) = ctx.use_state(|| false);
+        let group = if *expanded {
+            vec![e::text("b"), e::text("c"), e::text("c2")]
+        } else {
+            vec![e::text("b"), e::text("c")]
+        };
+        e::node([
+            e::button([e::text("Expand")])
+                .on_click(move |_| set_expanded.set(|expanded| *expanded = true)),
+            e::text("a"),
+            e::fragment(group),
+            e::text("d"),
+        ])
+    }
+
+    #[test]
+    fn fragments_add_no_node_of_their_own() {
+        let mut harness = TestHarness::new();
+        harness.mount(grouped.e(()));
+        // Every text sits directly below the root node, next to the button.
+        let snapshot = |texts: &[&str]| {
+            texts.iter().fold(
+                String::from("Node\n  Button\n    Text \"Expand\"\n"),
+                |snapshot, text| snapshot + &format!("  Text {:?}\n", text),
+            )
+        };
+        assert_eq!(harness.snapshot(), snapshot(&["a", "b", "c", "d"]));
+        harness.click("Expand");
+        assert_eq!(harness.snapshot(), snapshot(&["a", "b", "c", "c2", "d"]));
+    }
+
+    #[test]
+    fn captured_checks_flag_their_component() {
+        let mut harness = TestHarness::new();
+        harness.world.insert_resource(Volume(0));
+        let root = harness.mount(counted.e(()));
+        let threshold = 3;
+        harness
+            .context()
+            .cmp_checks
+            .entry(root.0)
+            .or_default()
+            .push(Box::new(move |world, _| {
+                world.get_resource::<Volume>().unwrap().0 > threshold
+            }));
+        harness.tick();
+        assert_eq!(harness.snapshot(), "Text \"Rendered 1 times\"\n");
+        harness.world.insert_resource(Volume(5));
+        harness.tick();
+        assert_eq!(harness.snapshot(), "Text \"Rendered 2 times\"\n");
+    }
+
+    fn mixer(ctx: Fctx) -> Element {
+        log("mixer");
+        let volume = ctx.use_resource::<Volume>().0;
+        let (presses, set_presses) = ctx.use_state(|| 0);
+        e::node([
+            e::button([e::text("Louder")]).on_click(move |world| {
+                world.get_resource_mut::<Volume>().unwrap().0 += 1;
+                set_presses.set(|presses| *presses += 1);
+            }),
+            e::text(format!("{} {}", volume, presses)),
+        ])
+    }
+
+    #[test]
+    fn resource_and_state_changes_together_render_once() {
+        let mut harness = TestHarness::new();
+        harness.world.insert_resource(Volume(0));
+        harness.mount(mixer.e(()));
+        assert_eq!(take_log(), ["mixer"]);
+        harness.click("Louder");
+        assert_eq!(take_log(), ["mixer"]);
+        assert_eq!(harness.context().stats().last_render_count, 1);
+        assert!(harness.snapshot().contains("Text \"1 1\""));
+    }
+
+    fn reset_button(_: Fctx, on_reset: &Callback) -> Element {
+        log("reset_button");
+        let on_reset = on_reset.clone();
+        e::button([e::text("Reset")]).on_click(move |_| on_reset.call(()))
+    }
+
+    fn resettable(ctx: Fctx) -> Element {
+        let (count, set_count) = ctx.use_state(|| 0);
+        let increment = set_count.clone();
+        let on_reset = Callback::clone(&ctx.use_memo((), move || {
+            Callback::new(move |()| set_count.set(|count| *count = 0))
+        }));
+        e::node([
+            e::button([e::text("Increment")]).on_click(move |_| increment.set(|count| *count += 1)),
+            e::text(count.to_string()),
+            reset_button.memo_e(on_reset),
+        ])
+    }
+
+    #[test]
+    fn stable_callback_props_keep_memoized_children_rendered() {
+        let mut harness = TestHarness::new();
+        harness.mount(resettable.e(()));
+        assert_eq!(take_log(), ["reset_button"]);
+        harness.click("Increment");
+        assert!(take_log().is_empty());
+        assert!(harness.snapshot().contains("Text \"1\""));
+        harness.click("Reset");
+        assert!(take_log().is_empty());
+        assert!(harness.snapshot().contains("Text \"0\""));
+    }
+
+    fn sidebar(_: Fctx) -> Element {
+        e::node([e::text("Title"), e::node([counted.e(()), e::text("Body")])])
+    }
+
+    #[test]
+    fn remounting_a_subtree_leaves_no_entities_behind() {
+        let mut harness = TestHarness::new();
+        let mut root = harness.mount(e::node(Vec::new()));
+        let baseline = harness.world.entities().len();
+        for _ in 0..10 {
+            root = harness.replace_root(root, e::node([sidebar.e(())]));
+            root = harness.replace_root(root, e::node(Vec::new()));
+        }
+        assert_eq!(harness.world.entities().len(), baseline);
+    }
+
+    fn greeting(_: Fctx) -> Element {
+        e::text("Hello")
+    }
+
+    fn greeting_card(_: Fctx) -> Element {
+        greeting.e(())
+    }
+
+    #[test]
+    fn primitive_of_finds_the_text_a_component_rendered() {
+        let mut world = TestHarness::new().world;
+        let mut ctx = Context::new();
+        let root = ctx.mount_root(greeting_card.e(()), &mut Dom { world: &mut world });
+        let text = ctx.primitive_of(root.id(), &world).unwrap();
+        assert_eq!(
+            world.get::<Text>(text.0).unwrap().sections[0].value,
+            "Hello"
+        );
+    }
+
+    fn maybe_middle(_: Fctx, shown: &bool) -> Option<Element> {
+        if *shown {
+            Some(e::text("Middle"))
+        } else {
+            None
+        }
+    }
+
+    fn optional_middle(ctx: Fctx) -> Element {
+        let (shown, set_shown) = ctx.use_state(|| true);
+        e::node([
+            e::button([e::text("Toggle")])
+                .on_click(move |_| set_shown.set(|shown| *shown = !*shown)),
+            e::text("Left"),
+            maybe_middle.e(*shown),
+            e::text("Right"),
+        ])
+    }
+
+    #[test]
+    fn absent_children_keep_their_siblings_in_place() {
+        let mut harness = TestHarness::new();
+        harness.mount(optional_middle.e(()));
+        let left = text_entity(&mut harness, "Left");
+        let right = text_entity(&mut harness, "Right");
+        harness.click("Toggle");
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Button\n    Text \"Toggle\"\n  Text \"Left\"\n  Text \"Right\"\n",
+        );
+        harness.click("Toggle");
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Button\n    Text \"Toggle\"\n  Text \"Left\"\n  Text \"Middle\"\n  \
+             Text \"Right\"\n",
+        );
+        assert_eq!(text_entity(&mut harness, "Left"), left);
+        assert_eq!(text_entity(&mut harness, "Right"), right);
+    }
+
+    fn pure_with_closures(_: Fctx) -> Element {
+        e::node([
+            e::raw(
+                |entity| {
+                    entity.insert_bundle(NodeBundle::default());
+                },
+                Vec::new(),
+            ),
+            e::text_input("Name", Callback::new(|_| {})),
+            e::button([e::text("Go")]).on_click(|_| {}),
+        ])
+    }
+
+    #[test]
+    fn strict_mode_leaves_out_closures() {
+        let mut harness = TestHarness::new();
+        harness.world.insert_resource(StrictMode);
+        harness.mount(pure_with_closures.e(()));
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Raw\n  TextInput \"Name\"\n  Button\n    Text \"Go\"\n",
+        );
+    }
+}
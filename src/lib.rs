@@ -1,50 +1,233 @@
+#[cfg(feature = "dev_overlay")]
+mod dev_overlay;
+mod diagnostics;
 mod dom;
 mod fctx;
+mod focus;
 mod internal;
+mod macros;
+mod measure;
+mod persist;
+mod scroll;
+mod selection;
+mod shortcuts;
+mod testing;
+mod text_input;
 
 use bevy::{
-    prelude::{AppBuilder, AssetServer, Handle, IntoExclusiveSystem, Plugin, World},
+    log::warn,
+    prelude::{
+        AppBuilder, AssetServer, CoreStage, Handle, IntoExclusiveSystem, IntoSystem,
+        ParallelSystemDescriptorCoercion, Plugin, World,
+    },
     text::Font,
+    transform::TransformSystem,
+    ui::UiSystem,
 };
+use std::panic::{self, AssertUnwindSafe};
 
-use internal::Element;
+use internal::{Element, MountedRootId};
 
-use prelude::{Context, Dom};
+use prelude::{
+    Context, Diagnostic, Dom, HookedDiagnostics, ShortcutRegistry, StyleSheet, Theme, UiScale,
+};
 
 pub mod prelude {
     use super::*;
-    pub use fctx::Fctx;
-    pub use internal::{ComponentFunc, Context, Element};
+    pub use fctx::{
+        entities_with, AsyncState, BatchScope, Cleanup, Fctx, IntervalReset, Lens, Ref, RefHandle,
+        Setter, SpringConfig, StateSetter, TimerControls,
+    };
+    pub use internal::{
+        Callback, ComponentFunc, Context, ContextStats, DiffEvent, Element, GridConfig, Key,
+        MountedId, MountedRootId, RenderProp, StatefulComponent, StrictMode,
+    };
     pub mod e {
-        pub use super::internal::{node, text};
+        pub use super::internal::{
+            any, button, center, column, columns, divider, fragment, grid, image, keyed_list,
+            labeled_field, lazy, lazy_unmounting, node, overlay, raw, row, scroll, slot, spacer,
+            split, styled_image, styled_node, styled_text, suspense, text, text_input,
+        };
     }
-    pub use crate::HookedUiPlugin;
-    pub use dom::{Dom, PrimitiveData, PrimitiveId, PrimitiveKind};
+    pub use crate::{view, HookedPaused, HookedRoot, HookedRoots, HookedUiPlugin};
+    #[cfg(feature = "dev_overlay")]
+    pub use dev_overlay::HookedDevOverlay;
+    pub use diagnostics::{Diagnostic, DiagnosticsHandler, HookedDiagnostics};
+    pub use dom::{
+        ClickHandler, Dom, Primitive, PrimitiveData, PrimitiveId, PrimitiveKind, PrimitiveNode,
+        RawSpawn, Role, Semantics, StyleClass, StyleSheet, TextOverrides, Theme, UiScale,
+        UiTransform,
+    };
+    pub use focus::Focused;
+    pub use persist::PersistedState;
+    pub use scroll::{ScrollController, ScrollViewport};
+    pub use selection::CopyText;
+    pub use shortcuts::{ActionId, KeyCombo, ShortcutRegistry};
+    pub use testing::TestHarness;
+}
+
+pub struct HookedUiPlugin {
+    roots: Vec<fn() -> Element>,
+    font: Option<FontSource>,
+}
+
+enum FontSource {
+    Path(String),
+    Handle(Handle<Font>),
 }
 
-pub struct HookedUiPlugin(pub fn() -> Element);
+impl HookedUiPlugin {
+    /// Mounts `root`, rendering text with Bevy's default font handle. Nothing is loaded into
+    /// it, so either set that asset or pick a font with [`HookedUiPlugin::with_font`].
+    pub fn new(root: fn() -> Element) -> Self {
+        Self {
+            roots: vec![root],
+            font: None,
+        }
+    }
+
+    /// Mounts `root`, rendering text with the font at `path`, loaded through the `AssetServer`.
+    pub fn with_font(root: fn() -> Element, path: impl Into<String>) -> Self {
+        Self {
+            roots: vec![root],
+            font: Some(FontSource::Path(path.into())),
+        }
+    }
+
+    /// Mounts `root`, rendering text with an already loaded font.
+    pub fn with_font_handle(root: fn() -> Element, font: Handle<Font>) -> Self {
+        Self {
+            roots: vec![root],
+            font: Some(FontSource::Handle(font)),
+        }
+    }
+
+    /// Mounts `root` as well, independently of the others, e.g. for a HUD kept apart from the
+    /// main tree.
+    pub fn add_root(mut self, root: fn() -> Element) -> Self {
+        self.roots.push(root);
+        self
+    }
+}
+
+/// While this resource holds `true`, the UI is frozen: nothing re-renders and effects stay queued.
+/// See [`Context::resume`] for what happens once it is set back to `false`.
+pub struct HookedPaused(pub bool);
+
+/// The first root mounted by [`HookedUiPlugin`], for re-rendering or replacing it through the
+/// [`Context`] non-send resource. [`Context::replace_root`] returns the id to store back.
+/// See [`HookedRoots`] for apps with several roots.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HookedRoot(pub MountedRootId);
+
+/// The roots mounted by [`HookedUiPlugin`] in the order they were added, the first being the
+/// one in [`HookedRoot`]. Replacing a root means storing its new id back in both.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HookedRoots(pub Vec<MountedRootId>);
 
 pub(crate) struct FontHandle(Handle<Font>);
 
+/// Inserts the resources rendering relies on, keeping any the app already inserted.
+pub(crate) fn insert_resources(world: &mut World, font: Handle<Font>) {
+    world.insert_resource(FontHandle(font));
+    world.get_resource_or_insert_with(Theme::default);
+    world.get_resource_or_insert_with(StyleSheet::default);
+    world.get_resource_or_insert_with(UiScale::default);
+    world.get_resource_or_insert_with(ShortcutRegistry::default);
+    world.get_resource_or_insert_with(|| HookedPaused(false));
+    world.get_resource_or_insert_with(focus::Focused::default);
+    world.get_resource_or_insert_with(HookedDiagnostics::default);
+    world.insert_resource(measure::TextMeasureCache::default());
+}
+
 impl Plugin for HookedUiPlugin {
     fn build(&self, app: &mut AppBuilder) {
         let mut ctx = Context::new();
         let world = app.world_mut();
 
-        let font_asset = world
-            .get_resource::<AssetServer>()
-            .unwrap()
-            .load("FiraMono-Medium.ttf");
+        let font_asset = match &self.font {
+            Some(FontSource::Path(path)) => world
+                .get_resource::<AssetServer>()
+                .unwrap()
+                .load(path.as_str()),
+            Some(FontSource::Handle(handle)) => handle.clone(),
+            None => {
+                warn!(
+                    "bevy_hooked: no font was picked, text renders nothing until one is loaded \
+                     into the default font handle, see `HookedUiPlugin::with_font`"
+                );
+                Handle::default()
+            }
+        };
+
+        insert_resources(world, font_asset);
 
-        world.insert_resource(FontHandle(font_asset));
+        #[cfg(feature = "diff_events")]
+        app.add_event::<prelude::DiffEvent>();
 
-        ctx.mount_root((self.0)(), &mut Dom { world, cursor: 0 });
+        app.add_system(fctx::tick_interval_counters.system());
+        app.add_system(fctx::tick_hook_timers.system());
+        app.add_system(fctx::poll_async_tasks.system());
+        app.add_system(fctx::step_springs.system());
+        app.add_system(fctx::track_hover_delays.system());
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            dom::apply_ui_transforms
+                .system()
+                .after(UiSystem::Flex)
+                .before(TransformSystem::TransformPropagate),
+        );
+        app.add_system(shortcuts::dispatch_shortcuts.system());
+        app.add_system(focus::focus_on_click.system());
+        app.add_system(text_input::edit_text_inputs.system());
+        app.add_system(measure::invalidate_text_measurements.system());
+        app.add_system(scroll::scroll_on_wheel.system());
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            scroll::resolve_scroll_requests
+                .system()
+                .after(TransformSystem::TransformPropagate),
+        );
+        app.add_event::<prelude::CopyText>()
+            .add_system(selection::drag_text_selection.system())
+            .add_system(selection::highlight_text_selection.system())
+            .add_system(selection::copy_text_selection.system());
+
+        let world = app.world_mut();
+        let roots = self
+            .roots
+            .iter()
+            .map(|root| ctx.mount_root(root(), &mut Dom { world }))
+            .collect::<Vec<_>>();
+        world.insert_resource(HookedRoot(roots[0]));
+        world.insert_resource(HookedRoots(roots));
         app.insert_non_send_resource(ctx);
+        let mut was_paused = false;
         app.add_system(
-            (|world: &mut World| {
+            (move |world: &mut World| {
+                // Apps that never pause may well remove the resource.
+                let paused = world
+                    .get_resource::<HookedPaused>()
+                    .map_or(false, |paused| paused.0);
+                if paused {
+                    was_paused = true;
+                    return;
+                }
                 let mut ctx = world.remove_non_send::<Context>().unwrap();
 
-                ctx.process_messages(world);
+                let resume = std::mem::replace(&mut was_paused, false);
+                // The context is put back even if a render panics, so one bad frame doesn't take
+                // the UI down for good. The panicking subtree may be left half-updated.
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    if resume {
+                        ctx.resume(world);
+                    }
+                    ctx.process_messages(world);
+                }));
+                if let Err(payload) = result {
+                    let message = diagnostics::panic_message(&*payload);
+                    diagnostics::report(world, Diagnostic::RenderPanicked { message });
+                }
 
                 world.insert_non_send(ctx);
             })
@@ -52,3 +235,30 @@ impl Plugin for HookedUiPlugin {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prelude::e, testing::test_app};
+    use bevy::text::Text;
+
+    #[test]
+    fn plugin_mounts_every_root() {
+        let mut app = test_app();
+        app.add_plugin(HookedUiPlugin::new(|| e::text("Main")).add_root(|| e::text("HUD")));
+        let world = app.world_mut();
+        let roots = world.get_resource::<HookedRoots>().unwrap().clone();
+        assert_eq!(roots.0.len(), 2);
+        assert_eq!(
+            *world.get_resource::<HookedRoot>().unwrap(),
+            HookedRoot(roots.0[0])
+        );
+        let mut texts = world
+            .query::<&Text>()
+            .iter(world)
+            .map(|text| text.sections[0].value.clone())
+            .collect::<Vec<_>>();
+        texts.sort();
+        assert_eq!(texts, ["HUD", "Main"]);
+    }
+}
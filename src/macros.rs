@@ -0,0 +1,122 @@
+/// Builds an [`Element`](crate::prelude::Element) tree, expanding to the same `e::` calls
+/// written by hand.
+///
+/// ```
+/// use bevy_hooked::prelude::*;
+///
+/// # fn counter(_: Fctx) -> Element { e::text("0") }
+/// # fn blinker(_: Fctx, _: &f32) -> Element { e::text("*") }
+/// let element = view! {
+///     Node {
+///         Text("Counters"),
+///         Button { Text("Reset") },
+///         counter(),
+///         blinker(1.).with_key(Key(1)),
+///         { e::spacer() },
+///     }
+/// };
+/// ```
+///
+/// `Node { .. }` and `Button { .. }` take a comma separated list of children, `Text(..)` and
+/// `Image(..)` take what [`e::text`](crate::prelude::e::text) and
+/// [`e::image`](crate::prelude::e::image) do. Any other name is a component called with its
+/// props, and anything in braces is used as an element as is. Children can be keyed with
+/// `.with_key(..)`.
+#[macro_export]
+macro_rules! view {
+    (@list [$($done:expr,)*]) => {
+        vec![$($done,)*]
+    };
+    (@list [$($done:expr,)*]
+        $name:ident { $($children:tt)* } $(.with_key($key:expr))? $(, $($rest:tt)*)?
+    ) => {
+        $crate::view!(@list [
+            $($done,)*
+            $crate::view!($name { $($children)* })$(.with_key($key))?,
+        ] $($($rest)*)?)
+    };
+    (@list [$($done:expr,)*]
+        $name:ident ( $($args:tt)* ) $(.with_key($key:expr))? $(, $($rest:tt)*)?
+    ) => {
+        $crate::view!(@list [
+            $($done,)*
+            $crate::view!($name ( $($args)* ))$(.with_key($key))?,
+        ] $($($rest)*)?)
+    };
+    (@list [$($done:expr,)*]
+        { $element:expr } $(.with_key($key:expr))? $(, $($rest:tt)*)?
+    ) => {
+        $crate::view!(@list [$($done,)* ($element)$(.with_key($key))?,] $($($rest)*)?)
+    };
+    (Node { $($children:tt)* }) => {
+        $crate::prelude::e::node($crate::view!(@list [] $($children)*))
+    };
+    (Button { $($children:tt)* }) => {
+        $crate::prelude::e::button($crate::view!(@list [] $($children)*))
+    };
+    (Text($text:expr)) => {
+        $crate::prelude::e::text($text)
+    };
+    (Image($texture:expr)) => {
+        $crate::prelude::e::image($texture)
+    };
+    ({ $element:expr }) => {
+        $element
+    };
+    ($component:ident ( $($prop:expr),* $(,)? )) => {
+        $crate::prelude::ComponentFunc::e(&$component, ($($prop,)*))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    fn counter(_: Fctx) -> Element {
+        e::text("0")
+    }
+
+    fn labeled(_: Fctx, label: &&'static str, value: &u32) -> Element {
+        e::text(format!("{} {}", label, value))
+    }
+
+    fn by_hand() -> Element {
+        e::node([
+            e::text("Counters"),
+            e::button([e::text("Reset")]),
+            counter.e(()),
+            labeled.e(("Score", 3)).with_key(Key(1)),
+            e::spacer(),
+        ])
+    }
+
+    fn by_macro() -> Element {
+        view! {
+            Node {
+                Text("Counters"),
+                Button { Text("Reset") },
+                counter(),
+                labeled("Score", 3).with_key(Key(1)),
+                { e::spacer() },
+            }
+        }
+    }
+
+    #[test]
+    fn expands_to_the_hand_written_tree() {
+        assert!(by_macro().structurally_eq(&by_hand()));
+        // Structural equality ignores props, the rendered trees don't.
+        let mut hand = TestHarness::new();
+        hand.mount(by_hand());
+        let mut expanded = TestHarness::new();
+        expanded.mount(by_macro());
+        assert_eq!(expanded.snapshot(), hand.snapshot());
+    }
+
+    #[test]
+    fn single_elements_expand_as_is() {
+        assert!(view! { Text("Hi") }.structurally_eq(&e::text("Hi")));
+        assert!(view! { counter() }.structurally_eq(&counter.e(())));
+        assert!(view! { Node {} }.structurally_eq(&e::node(Vec::new())));
+    }
+}
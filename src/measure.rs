@@ -0,0 +1,133 @@
+use std::sync::Mutex;
+
+use ab_glyph::{Font as _, FontArc, PxScale, ScaleFont};
+use bevy::{
+    asset::{AssetEvent, HandleId},
+    prelude::*,
+    text::Font,
+    utils::HashMap,
+};
+
+/// How many measurements are kept. Once full, the least recently used half is dropped.
+const CAPACITY: usize = 4096;
+
+type MeasureKey = (String, HandleId, u32);
+
+/// Single line text sizes, keyed by string, font and font size.
+#[derive(Default)]
+pub(crate) struct TextMeasureCache {
+    inner: Mutex<CacheInner>,
+}
+
+#[derive(Default)]
+struct CacheInner {
+    entries: HashMap<MeasureKey, (Vec2, u64)>,
+    clock: u64,
+}
+
+impl TextMeasureCache {
+    /// Measures `text`, or returns `None` if the font isn't loaded yet.
+    pub(crate) fn measure(
+        &self,
+        fonts: &Assets<Font>,
+        font: &Handle<Font>,
+        text: &str,
+        font_size: f32,
+    ) -> Option<Vec2> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+        let clock = inner.clock;
+        let key = (text.to_string(), font.id, font_size.to_bits());
+        if let Some((size, last_used)) = inner.entries.get_mut(&key) {
+            *last_used = clock;
+            return Some(*size);
+        }
+        let size = measure_uncached(&fonts.get(font)?.font, text, font_size);
+        if inner.entries.len() >= CAPACITY {
+            let mut ages = inner
+                .entries
+                .values()
+                .map(|(_, last_used)| *last_used)
+                .collect::<Vec<_>>();
+            let (_, &mut cutoff, _) = ages.select_nth_unstable(CAPACITY / 2);
+            inner
+                .entries
+                .retain(|_, (_, last_used)| *last_used > cutoff);
+        }
+        inner.entries.insert(key, (size, clock));
+        Some(size)
+    }
+
+    fn invalidate(&self, font: HandleId) {
+        self.inner
+            .lock()
+            .unwrap()
+            .entries
+            .retain(|(_, id, _), _| *id != font);
+    }
+}
+
+fn measure_uncached(font: &FontArc, text: &str, font_size: f32) -> Vec2 {
+    let font = font.as_scaled(PxScale::from(font_size));
+    let mut width = 0.;
+    let mut previous = None;
+    for c in text.chars() {
+        let glyph = font.glyph_id(c);
+        if let Some(previous) = previous {
+            width += font.kern(previous, glyph);
+        }
+        width += font.h_advance(glyph);
+        previous = Some(glyph);
+    }
+    Vec2::new(width, font.height())
+}
+
+/// The character boundary in single line `text` closest to `x`, measured from its left edge.
+pub(crate) fn char_index_at(font: &FontArc, text: &str, font_size: f32, x: f32) -> usize {
+    let font = font.as_scaled(PxScale::from(font_size));
+    let mut left = 0.;
+    let mut previous = None;
+    for (index, c) in text.chars().enumerate() {
+        let glyph = font.glyph_id(c);
+        if let Some(previous) = previous {
+            left += font.kern(previous, glyph);
+        }
+        let advance = font.h_advance(glyph);
+        if x < left + advance / 2. {
+            return index;
+        }
+        left += advance;
+        previous = Some(glyph);
+    }
+    text.chars().count()
+}
+
+/// Drops the measurements of fonts that were reloaded or unloaded.
+pub(crate) fn invalidate_text_measurements(
+    cache: Res<TextMeasureCache>,
+    mut events: EventReader<AssetEvent<Font>>,
+) {
+    for event in events.iter() {
+        match event {
+            AssetEvent::Modified { handle } | AssetEvent::Removed { handle } => {
+                cache.invalidate(handle.id)
+            }
+            AssetEvent::Created { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn char_indices_follow_glyph_advances() {
+        let font =
+            FontArc::try_from_slice(include_bytes!("../assets/FiraMono-Medium.ttf")).unwrap();
+        let before_l = measure_uncached(&font, "Wi", 20.).x;
+        assert_eq!(char_index_at(&font, "Wil", 20., 0.), 0);
+        assert_eq!(char_index_at(&font, "Wil", 20., before_l + 1.), 2);
+        assert_eq!(char_index_at(&font, "Wil", 20., 1000.), 3);
+    }
+}
@@ -0,0 +1,40 @@
+use bevy::{
+    prelude::{Entity, World},
+    reflect::Reflect,
+    utils::HashMap,
+};
+
+/// Values of persisted state, by the key passed to
+/// [`Fctx::use_persisted_state`](crate::prelude::Fctx::use_persisted_state).
+///
+/// [`PersistedState::capture`] snapshots the mounted state, e.g. on exit. Inserted as a resource
+/// before the UI mounts, usually after loading it back, it seeds the state of the components
+/// using the same keys. Writing it to disk is up to the app, e.g. with Bevy's
+/// `ReflectSerializer` and the app's type registry.
+#[derive(Default)]
+pub struct PersistedState {
+    pub values: HashMap<String, Box<dyn Reflect>>,
+}
+
+impl PersistedState {
+    /// Captures the current value of every mounted persisted state.
+    pub fn capture(world: &mut World) -> Self {
+        let mut query = world.query::<(Entity, &PersistedKeys)>();
+        let world = &*world;
+        let mut values = HashMap::default();
+        for (entity, keys) in query.iter(world) {
+            for (key, read) in &keys.0 {
+                if let Some(value) = read(world, entity) {
+                    values.insert(key.clone(), value);
+                }
+            }
+        }
+        Self { values }
+    }
+}
+
+/// The persisted states of a component, with how to read each one back.
+#[derive(Default)]
+pub(crate) struct PersistedKeys(
+    pub(crate) Vec<(String, fn(&World, Entity) -> Option<Box<dyn Reflect>>)>,
+);
@@ -0,0 +1,157 @@
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+};
+
+use crate::{dom::PrimitiveId, fctx::Setter, internal::MountedId};
+
+/// Marks a node as the viewport of a [`scroll`](crate::prelude::e::scroll) area, pointing at
+/// the component holding its scroll position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScrollViewport(pub(crate) MountedId);
+
+/// The scroll position of a scroll area, linked to the component calling
+/// [`Fctx::use_scroll`](crate::prelude::Fctx::use_scroll).
+#[derive(Default)]
+pub(crate) struct ScrollState {
+    pub(crate) offset: f32,
+    request: Option<ScrollRequest>,
+}
+
+#[derive(Clone, Copy)]
+enum ScrollRequest {
+    To(f32),
+    Bottom,
+    IntoView(PrimitiveId),
+}
+
+/// Scrolls a scroll area from code, e.g. to follow a log as it grows. Requests are resolved
+/// once the next layout has sized the content, so the new offset shows up a frame later.
+#[derive(Clone)]
+pub struct ScrollController {
+    pub(crate) owner: MountedId,
+    pub(crate) offset: f32,
+    pub(crate) setter: Setter<ScrollState>,
+}
+
+impl ScrollController {
+    /// How far the content is scrolled down, in pixels.
+    pub fn offset(&self) -> f32 {
+        self.offset
+    }
+
+    /// Scrolls to `offset`, clamped to the scrollable range.
+    pub fn scroll_to(&self, offset: f32) {
+        self.request(ScrollRequest::To(offset));
+    }
+
+    pub fn scroll_to_bottom(&self) {
+        self.request(ScrollRequest::Bottom);
+    }
+
+    /// Scrolls just far enough for `primitive`, somewhere inside the area, to be fully visible.
+    /// If it is taller than the viewport, its top is shown.
+    pub fn scroll_into_view(&self, primitive: PrimitiveId) {
+        self.request(ScrollRequest::IntoView(primitive));
+    }
+
+    fn request(&self, request: ScrollRequest) {
+        self.setter
+            .set(move |mut state| state.request = Some(request));
+    }
+}
+
+/// How far a wheel scrolling by lines moves, in pixels per line.
+const PIXELS_PER_LINE: f32 = 20.;
+
+/// Scrolls the innermost scroll area under the cursor with the mouse wheel.
+pub(crate) fn scroll_on_wheel(
+    mut wheel: EventReader<MouseWheel>,
+    windows: Res<Windows>,
+    viewports: Query<(&ScrollViewport, &Node, &GlobalTransform)>,
+    mut states: Query<&mut ScrollState>,
+) {
+    let delta = wheel
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y * PIXELS_PER_LINE,
+            MouseScrollUnit::Pixel => event.y,
+        })
+        .sum::<f32>();
+    if delta == 0. {
+        return;
+    }
+    let cursor = match windows.get_primary().and_then(|w| w.cursor_position()) {
+        Some(cursor) => cursor,
+        None => return,
+    };
+    // Nested areas lie within their parents, so the innermost is the smallest.
+    let hovered = viewports
+        .iter()
+        .filter(|(_, node, transform)| {
+            let min = transform.translation.truncate() - node.size / 2.;
+            let max = min + node.size;
+            cursor.x >= min.x && cursor.x <= max.x && cursor.y >= min.y && cursor.y <= max.y
+        })
+        .min_by(|(_, a, _), (_, b, _)| {
+            (a.size.x * a.size.y)
+                .partial_cmp(&(b.size.x * b.size.y))
+                .unwrap()
+        });
+    if let Some((viewport, _, _)) = hovered {
+        if let Ok(mut state) = states.get_mut(viewport.0.entity()) {
+            let from = match state.request {
+                Some(ScrollRequest::To(offset)) => offset,
+                _ => state.offset,
+            };
+            // Wheeling up gives a positive delta and scrolls towards the top.
+            state.request = Some(ScrollRequest::To((from - delta).max(0.)));
+        }
+    }
+}
+
+/// Turns scroll requests into offsets, using the laid out sizes and positions.
+pub(crate) fn resolve_scroll_requests(
+    viewports: Query<(&ScrollViewport, &Node, &GlobalTransform, &Children)>,
+    nodes: Query<(&Node, &GlobalTransform)>,
+    mut states: Query<&mut ScrollState>,
+) {
+    for (viewport, node, transform, children) in viewports.iter() {
+        let mut state = match states.get_mut(viewport.0.entity()) {
+            Ok(state) => state,
+            Err(_) => continue,
+        };
+        let request = match state.request {
+            // Not laid out yet.
+            Some(_) if node.size.y == 0. => continue,
+            Some(request) => request,
+            None => continue,
+        };
+        let content = match children.first().and_then(|c| nodes.get(*c).ok()) {
+            Some((content, _)) => content.size.y,
+            None => 0.,
+        };
+        let max = (content - node.size.y).max(0.);
+        let offset = match request {
+            ScrollRequest::To(offset) => offset,
+            ScrollRequest::Bottom => max,
+            ScrollRequest::IntoView(target) => match nodes.get(target.entity()) {
+                Ok((target_node, target_transform)) => {
+                    // Bevy's UI y axis points up, and scrolling down moves the content up.
+                    let top = transform.translation.y + node.size.y / 2.;
+                    let bottom = transform.translation.y - node.size.y / 2.;
+                    let target_top = target_transform.translation.y + target_node.size.y / 2.;
+                    let target_bottom = target_transform.translation.y - target_node.size.y / 2.;
+                    let mut shift = (bottom - target_bottom).max(0.);
+                    if target_top + shift > top {
+                        shift -= target_top + shift - top;
+                    }
+                    state.offset + shift
+                }
+                Err(_) => state.offset,
+            },
+        };
+        state.offset = offset.clamp(0., max);
+        state.request = None;
+    }
+}
@@ -0,0 +1,197 @@
+use bevy::{
+    prelude::*,
+    text::{Font, Text, TextSection, TextStyle},
+};
+
+use crate::{
+    dom::{Theme, Themed},
+    measure::char_index_at,
+};
+
+/// Sent on Ctrl+C with the text selected in a selectable text primitive. Bevy has no clipboard
+/// access of its own, so putting it on the clipboard is up to the app.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CopyText(pub String);
+
+/// The selected range of a selectable text, in characters. Empty when `anchor == cursor`.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct TextSelection {
+    anchor: usize,
+    cursor: usize,
+}
+
+impl TextSelection {
+    pub(crate) fn range(&self) -> (usize, usize) {
+        (self.anchor.min(self.cursor), self.anchor.max(self.cursor))
+    }
+}
+
+fn full_text(text: &Text) -> String {
+    text.sections
+        .iter()
+        .map(|section| &*section.value)
+        .collect()
+}
+
+fn byte_index(text: &str, chars: usize) -> usize {
+    text.char_indices()
+        .nth(chars)
+        .map_or(text.len(), |(byte, _)| byte)
+}
+
+/// Selects by dragging over a single line of text. Texts whose font isn't loaded yet can't be
+/// selected.
+pub(crate) fn drag_text_selection(
+    windows: Res<Windows>,
+    mouse: Res<Input<MouseButton>>,
+    fonts: Res<Assets<Font>>,
+    mut dragging: Local<Option<Entity>>,
+    mut texts: Query<(Entity, &Node, &GlobalTransform, &Text, &mut TextSelection)>,
+) {
+    if !mouse.pressed(MouseButton::Left) {
+        *dragging = None;
+        return;
+    }
+    let cursor = match windows.get_primary().and_then(|w| w.cursor_position()) {
+        Some(cursor) => cursor,
+        None => return,
+    };
+    for (entity, node, transform, text, mut selection) in texts.iter_mut() {
+        let left = transform.translation.x - node.size.x / 2.;
+        let bottom = transform.translation.y - node.size.y / 2.;
+        let style = match text.sections.first() {
+            Some(section) => &section.style,
+            None => continue,
+        };
+        let font = match fonts.get(&style.font) {
+            Some(font) => &font.font,
+            None => continue,
+        };
+        let index = char_index_at(font, &full_text(text), style.font_size, cursor.x - left);
+        if mouse.just_pressed(MouseButton::Left) {
+            let inside = cursor.x >= left
+                && cursor.x <= left + node.size.x
+                && cursor.y >= bottom
+                && cursor.y <= bottom + node.size.y;
+            if inside {
+                *dragging = Some(entity);
+                *selection = TextSelection {
+                    anchor: index,
+                    cursor: index,
+                };
+            } else if selection.anchor != selection.cursor {
+                selection.anchor = selection.cursor;
+            }
+        } else if *dragging == Some(entity) && selection.cursor != index {
+            selection.cursor = index;
+        }
+    }
+}
+
+/// Splits selectable texts into sections so the selected part is drawn in the theme's
+/// selection color.
+pub(crate) fn highlight_text_selection(
+    theme: Res<Theme>,
+    mut texts: Query<
+        (&mut Text, &TextSelection, Option<&Themed>),
+        Or<(Changed<Text>, Changed<TextSelection>)>,
+    >,
+) {
+    for (mut text, selection, themed) in texts.iter_mut() {
+        let full = full_text(&text);
+        let base = TextStyle {
+            color: themed
+                .and_then(|themed| themed.0.color)
+                .unwrap_or(theme.text_color),
+            ..text.sections[0].style.clone()
+        };
+        let highlight = TextStyle {
+            color: theme.selection_color,
+            ..base.clone()
+        };
+        let (start, end) = selection.range();
+        let (start, end) = (byte_index(&full, start), byte_index(&full, end));
+        let mut sections = [
+            (&full[..start], &base),
+            (&full[start..end], &highlight),
+            (&full[end..], &base),
+        ]
+        .iter()
+        .filter(|(value, _)| !value.is_empty())
+        .map(|(value, style)| TextSection {
+            value: value.to_string(),
+            style: (*style).clone(),
+        })
+        .collect::<Vec<_>>();
+        if sections.is_empty() {
+            sections.push(TextSection {
+                value: String::new(),
+                style: base.clone(),
+            });
+        }
+        // Writing unconditionally would mark the text changed and run this again next frame.
+        let unchanged = text.sections.len() == sections.len()
+            && text
+                .sections
+                .iter()
+                .zip(&sections)
+                .all(|(a, b)| a.value == b.value && a.style.color == b.style.color);
+        if !unchanged {
+            text.sections = sections;
+        }
+    }
+}
+
+pub(crate) fn copy_text_selection(
+    keys: Res<Input<KeyCode>>,
+    texts: Query<(&Text, &TextSelection)>,
+    mut events: EventWriter<CopyText>,
+) {
+    let ctrl = keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl);
+    if !(ctrl && keys.just_pressed(KeyCode::C)) {
+        return;
+    }
+    for (text, selection) in texts.iter() {
+        let (start, end) = selection.range();
+        if start == end {
+            continue;
+        }
+        let full = full_text(text);
+        let selected = &full[byte_index(&full, start)..byte_index(&full, end)];
+        events.send(CopyText(selected.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::{e, TestHarness};
+
+    #[test]
+    fn theme_changes_keep_the_highlight() {
+        let mut harness = TestHarness::new();
+        harness.mount(e::text("hello").selectable());
+        let mut selections = harness.world.query::<&mut TextSelection>();
+        for mut selection in selections.iter_mut(&mut harness.world) {
+            *selection = TextSelection {
+                anchor: 0,
+                cursor: 2,
+            };
+        }
+        SystemStage::single(highlight_text_selection.system()).run(&mut harness.world);
+        harness.world.insert_resource(Theme {
+            text_color: Color::WHITE,
+            selection_color: Color::RED,
+            ..Theme::default()
+        });
+        harness.tick();
+        let mut texts = harness.world.query::<&Text>();
+        let text = texts.iter(&harness.world).next().unwrap();
+        let colors = text
+            .sections
+            .iter()
+            .map(|section| (section.value.as_str(), section.style.color))
+            .collect::<Vec<_>>();
+        assert_eq!(colors, [("he", Color::RED), ("llo", Color::WHITE)]);
+    }
+}
@@ -0,0 +1,162 @@
+use bevy::prelude::*;
+
+use crate::internal::MountedId;
+
+/// A key along with the modifiers that must be held for it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyCombo {
+    pub key: KeyCode,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl KeyCombo {
+    pub fn new(key: KeyCode) -> Self {
+        Self {
+            key,
+            ctrl: false,
+            shift: false,
+            alt: false,
+        }
+    }
+
+    pub fn ctrl(mut self) -> Self {
+        self.ctrl = true;
+        self
+    }
+
+    pub fn shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+
+    pub fn alt(mut self) -> Self {
+        self.alt = true;
+        self
+    }
+}
+
+/// Names what a shortcut does, e.g. for listing shortcuts in a help overlay.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ActionId(pub &'static str);
+
+struct Shortcut {
+    owner: MountedId,
+    combo: KeyCombo,
+    action: ActionId,
+    handler: Box<dyn Fn() + Send + Sync>,
+}
+
+/// Every shortcut registered with [`Fctx::use_shortcut`](crate::prelude::Fctx::use_shortcut).
+/// When several components register the same combo, only the one registered last fires.
+#[derive(Default)]
+pub struct ShortcutRegistry {
+    shortcuts: Vec<Shortcut>,
+}
+
+impl ShortcutRegistry {
+    /// The active shortcuts, in registration order.
+    pub fn shortcuts(&self) -> impl Iterator<Item = (KeyCombo, ActionId)> + '_ {
+        self.shortcuts.iter().map(|s| (s.combo, s.action))
+    }
+
+    /// The combos more than one action is registered for.
+    pub fn conflicts(&self) -> Vec<KeyCombo> {
+        let mut conflicts = Vec::new();
+        for (i, shortcut) in self.shortcuts.iter().enumerate() {
+            let taken = self.shortcuts[..i]
+                .iter()
+                .any(|other| other.combo == shortcut.combo);
+            if taken && !conflicts.contains(&shortcut.combo) {
+                conflicts.push(shortcut.combo);
+            }
+        }
+        conflicts
+    }
+
+    /// Replaces the shortcut `owner` has for `combo`, keeping its place.
+    pub(crate) fn register(
+        &mut self,
+        owner: MountedId,
+        combo: KeyCombo,
+        action: ActionId,
+        handler: Box<dyn Fn() + Send + Sync>,
+    ) {
+        let shortcut = Shortcut {
+            owner,
+            combo,
+            action,
+            handler,
+        };
+        match self
+            .shortcuts
+            .iter_mut()
+            .find(|s| s.owner == owner && s.combo == combo)
+        {
+            Some(existing) => *existing = shortcut,
+            None => self.shortcuts.push(shortcut),
+        }
+    }
+
+    pub(crate) fn unregister(&mut self, owner: MountedId) {
+        self.shortcuts.retain(|s| s.owner != owner);
+    }
+}
+
+pub(crate) fn dispatch_shortcuts(keys: Res<Input<KeyCode>>, registry: Res<ShortcutRegistry>) {
+    let held = |a, b| keys.pressed(a) || keys.pressed(b);
+    let ctrl = held(KeyCode::LControl, KeyCode::RControl);
+    let shift = held(KeyCode::LShift, KeyCode::RShift);
+    let alt = held(KeyCode::LAlt, KeyCode::RAlt);
+    for &key in keys.get_just_pressed() {
+        let combo = KeyCombo {
+            key,
+            ctrl,
+            shift,
+            alt,
+        };
+        if let Some(shortcut) = registry.shortcuts.iter().rev().find(|s| s.combo == combo) {
+            (shortcut.handler)();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::{e, ComponentFunc, Element, Fctx, TestHarness};
+
+    struct Count(u32);
+
+    fn counter(ctx: Fctx) -> Element {
+        let (count, set_count) = ctx.use_linked_state(|| Count(0));
+        ctx.use_shortcut(
+            KeyCombo::new(KeyCode::Up),
+            ActionId("increment"),
+            move || set_count.set(|mut count| count.0 += 1),
+        );
+        e::text(count.0.to_string())
+    }
+
+    #[test]
+    fn handlers_can_set_state() {
+        let mut harness = TestHarness::new();
+        harness.mount(counter.e(()));
+        let mut keys = Input::<KeyCode>::default();
+        keys.press(KeyCode::Up);
+        harness.world.insert_resource(keys);
+        SystemStage::single(dispatch_shortcuts.system()).run(&mut harness.world);
+        harness.tick();
+        assert_eq!(harness.snapshot(), "Text \"1\"\n");
+        assert_eq!(
+            harness
+                .world
+                .get_resource::<ShortcutRegistry>()
+                .unwrap()
+                .shortcuts()
+                .collect::<Vec<_>>(),
+            [(KeyCombo::new(KeyCode::Up), ActionId("increment"))],
+        );
+    }
+}
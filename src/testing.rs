@@ -0,0 +1,260 @@
+use std::mem;
+
+use bevy::{
+    asset::{AddAsset, AssetPlugin},
+    core::CorePlugin,
+    prelude::{App, AppBuilder, Children, ColorMaterial, Entity, Handle, Texture, World},
+    text::{Font, Text},
+    ui::Interaction,
+};
+
+use crate::{
+    dom::{Dom, PrimitiveKind, PrimitiveNode},
+    internal::{Context, Element, MountedRootId},
+};
+
+/// Renders components without an `App`, for testing them. Nothing but rendering runs: set up
+/// whatever systems would do, like elapsed time, through `world`.
+///
+/// ```
+/// use bevy_hooked::prelude::*;
+///
+/// struct Clicks(u32);
+///
+/// fn counter(ctx: Fctx) -> Element {
+///     let (clicks, set_clicks) = ctx.use_linked_state(|| Clicks(0));
+///     e::node([
+///         e::text(format!("Clicked {} times", clicks.0)),
+///         e::button([e::text("Click me")]).on_click(move |_| set_clicks.set(|mut c| c.0 += 1)),
+///     ])
+/// }
+///
+/// let mut harness = TestHarness::new();
+/// harness.mount(counter.e(()));
+/// harness.click("Click me");
+/// assert_eq!(
+///     harness.snapshot(),
+///     "Node\n  Text \"Clicked 1 times\"\n  Button\n    Text \"Click me\"\n",
+/// );
+/// ```
+pub struct TestHarness {
+    pub world: World,
+    ctx: Context,
+}
+
+impl TestHarness {
+    /// Sets up a world with the assets and resources rendering needs. Text uses the default font
+    /// handle, with nothing loaded into it.
+    pub fn new() -> Self {
+        let mut world = mem::take(test_app().world_mut());
+        crate::insert_resources(&mut world, Handle::default());
+        Self {
+            world,
+            ctx: Context::new(),
+        }
+    }
+
+    /// Mounts `element` as a new root, rendering it right away.
+    pub fn mount(&mut self, element: Element) -> MountedRootId {
+        self.ctx.mount_root(
+            element,
+            &mut Dom {
+                world: &mut self.world,
+            },
+        )
+    }
+
+    /// Mounts `element` as a new root taking over `existing` and its children, see
+    /// [`Context::mount_root_adopting`].
+    pub fn mount_adopting(&mut self, element: Element, existing: Entity) -> MountedRootId {
+        self.ctx.mount_root_adopting(
+            element,
+            existing,
+            &mut Dom {
+                world: &mut self.world,
+            },
+        )
+    }
+
+    /// Renders `element` in place of the root `id`, see [`Context::replace_root`].
+    pub fn replace_root(&mut self, id: MountedRootId, element: Element) -> MountedRootId {
+        self.ctx.replace_root(
+            id,
+            element,
+            &mut Dom {
+                world: &mut self.world,
+            },
+        )
+    }
+
+    /// Schedules re-rendering the whole root `id` on the next tick, see
+    /// [`Context::rerender_root`].
+    pub fn rerender_root(&mut self, id: MountedRootId) {
+        self.ctx.rerender_root(
+            id,
+            &mut Dom {
+                world: &mut self.world,
+            },
+        )
+    }
+
+    /// Catches up after frames the UI was paused for, see [`Context::resume`]. Render the
+    /// catching up with [`TestHarness::tick`].
+    pub fn resume(&mut self) {
+        self.ctx.resume(&mut self.world);
+    }
+
+    /// Applies what was set or flagged since the last tick, re-rendering what it affects, then
+    /// clears change detection the way the end of an app frame does.
+    pub fn tick(&mut self) {
+        self.ctx.process_messages(&mut self.world);
+        self.world.clear_trackers();
+    }
+
+    /// Clicks a button with a text child reading `label` and ticks, running its
+    /// [`Element::on_click`](crate::prelude::Element::on_click) handler.
+    ///
+    /// # Panics
+    /// If no such button is mounted.
+    pub fn click(&mut self, label: &str) {
+        let button = self
+            .find_button(label)
+            .unwrap_or_else(|| panic!("no button labeled {:?} is mounted", label));
+        *self.world.get_mut::<Interaction>(button).unwrap() = Interaction::Clicked;
+        self.tick();
+        if let Some(mut interaction) = self.world.get_mut::<Interaction>(button) {
+            *interaction = Interaction::None;
+        }
+    }
+
+    fn find_button(&mut self, label: &str) -> Option<Entity> {
+        let mut query = self.world.query::<(Entity, &PrimitiveKind, &Children)>();
+        let world = &self.world;
+        query
+            .iter(world)
+            .filter(|(_, kind, _)| **kind == PrimitiveKind::Button)
+            .find(|(_, _, children)| {
+                children.iter().any(|child| {
+                    world.get::<Text>(*child).map_or(false, |text| {
+                        text.sections
+                            .iter()
+                            .map(|section| section.value.as_str())
+                            .collect::<String>()
+                            == label
+                    })
+                })
+            })
+            .map(|(button, _, _)| button)
+    }
+
+    /// Lists the mounted primitives, one per line and indented under their parent, with the
+    /// value of text. See [`TestHarness::tree`] for asserting on the structure instead.
+    pub fn snapshot(&mut self) -> String {
+        let mut snapshot = String::new();
+        for root in self.tree() {
+            write_node(&root, 0, &mut snapshot);
+        }
+        snapshot
+    }
+
+    /// The mounted primitives, one tree per root primitive, see [`Dom::dump_tree`].
+    pub fn tree(&mut self) -> Vec<PrimitiveNode> {
+        Dom {
+            world: &mut self.world,
+        }
+        .dump_tree()
+    }
+
+    /// The context driving the harness, e.g. for replacing roots or reading its
+    /// [`stats`](Context::stats).
+    pub fn context(&mut self) -> &mut Context {
+        &mut self.ctx
+    }
+}
+
+/// An app with just the assets rendering needs, for tests that need more than the harness.
+pub(crate) fn test_app() -> AppBuilder {
+    let mut app = App::build();
+    app.add_plugin(CorePlugin::default())
+        .add_plugin(AssetPlugin::default())
+        .add_asset::<ColorMaterial>()
+        .add_asset::<Font>()
+        .add_asset::<Texture>();
+    app
+}
+
+impl Default for TestHarness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn write_node(node: &PrimitiveNode, depth: usize, snapshot: &mut String) {
+    snapshot.push_str(&"  ".repeat(depth));
+    snapshot.push_str(&format!("{:?}", node.kind));
+    if let Some(text) = &node.text {
+        snapshot.push_str(&format!(" {:?}", text));
+    }
+    snapshot.push('\n');
+    for child in &node.children {
+        write_node(child, depth + 1, snapshot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    struct Clicks(u32);
+
+    fn counter(ctx: Fctx) -> Element {
+        let (clicks, set_clicks) = ctx.use_linked_state(|| Clicks(0));
+        e::node([
+            e::text(format!("Clicked {} times", clicks.0)),
+            e::button([e::text("Click me")]).on_click(move |_| set_clicks.set(|mut c| c.0 += 1)),
+        ])
+    }
+
+    #[test]
+    fn mount_renders_right_away() {
+        let mut harness = TestHarness::new();
+        harness.mount(counter.e(()));
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Text \"Clicked 0 times\"\n  Button\n    Text \"Click me\"\n",
+        );
+    }
+
+    #[test]
+    fn tick_without_changes_keeps_the_tree() {
+        let mut harness = TestHarness::new();
+        harness.mount(counter.e(()));
+        let before = harness.snapshot();
+        harness.tick();
+        harness.tick();
+        assert_eq!(harness.snapshot(), before);
+    }
+
+    #[test]
+    fn click_applies_the_handler() {
+        let mut harness = TestHarness::new();
+        harness.mount(counter.e(()));
+        harness.click("Click me");
+        harness.click("Click me");
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Text \"Clicked 2 times\"\n  Button\n    Text \"Click me\"\n",
+        );
+        // The click was handled once, not again on every later tick.
+        harness.tick();
+        assert!(harness.snapshot().contains("Text \"Clicked 2 times\""));
+    }
+
+    #[test]
+    #[should_panic(expected = "no button labeled")]
+    fn click_without_a_button_panics() {
+        let mut harness = TestHarness::new();
+        harness.mount(counter.e(()));
+        harness.click("Reset");
+    }
+}
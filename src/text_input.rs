@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+
+use crate::{focus::Focused, internal::Callback};
+
+/// The text of a [`text_input`](crate::prelude::e::text_input) as last edited, which may run
+/// ahead of the value it was rendered with until the next render catches up.
+pub(crate) struct TextInputState {
+    pub(crate) value: String,
+    pub(crate) on_change: Option<Callback<String>>,
+}
+
+/// Types into the focused text input, and erases from it with backspace.
+pub(crate) fn edit_text_inputs(
+    mut chars: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    focused: Res<Focused>,
+    mut inputs: Query<(&mut TextInputState, &mut Text)>,
+) {
+    // Read even when nothing is focused, so keys typed meanwhile don't show up later.
+    let typed = chars
+        .iter()
+        .map(|event| event.char)
+        .filter(|c| !c.is_control())
+        .collect::<String>();
+    let (mut input, mut text) = match focused.0.and_then(|entity| inputs.get_mut(entity).ok()) {
+        Some(input) => input,
+        None => return,
+    };
+    let mut value = input.value.clone();
+    value.push_str(&typed);
+    if keys.just_pressed(KeyCode::Back) {
+        value.pop();
+    }
+    if value == input.value {
+        return;
+    }
+    text.sections[0].value = value.clone();
+    input.value = value.clone();
+    if let Some(on_change) = &input.on_change {
+        on_change.call(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::window::WindowId;
+
+    use super::*;
+    use crate::prelude::{e, ComponentFunc, Element, Fctx, TestHarness};
+
+    struct Name(String);
+
+    fn greeter(ctx: Fctx) -> Element {
+        let (name, set_name) = ctx.use_linked_state(|| Name(String::new()));
+        e::node([
+            e::text(format!("Hello, {}!", name.0)),
+            e::text_input(
+                name.0.clone(),
+                Callback::new(move |value| set_name.set(move |mut name| name.0 = value)),
+            ),
+        ])
+    }
+
+    #[test]
+    fn typing_sets_state_through_the_callback() {
+        let mut harness = TestHarness::new();
+        harness.mount(greeter.e(()));
+        let input = harness
+            .world
+            .query_filtered::<Entity, With<TextInputState>>()
+            .iter(&harness.world)
+            .next()
+            .unwrap();
+        let mut chars = Events::<ReceivedCharacter>::default();
+        for char in "Ann".chars() {
+            chars.send(ReceivedCharacter {
+                id: WindowId::primary(),
+                char,
+            });
+        }
+        harness.world.insert_resource(chars);
+        harness.world.insert_resource(Input::<KeyCode>::default());
+        harness.world.insert_resource(Focused(Some(input)));
+        SystemStage::single(edit_text_inputs.system()).run(&mut harness.world);
+        harness.tick();
+        assert_eq!(
+            harness.snapshot(),
+            "Node\n  Text \"Hello, Ann!\"\n  TextInput \"Ann\"\n",
+        );
+    }
+}